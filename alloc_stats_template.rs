@@ -1,90 +1,242 @@
 #![allow(unreachable_code)]
 {crate_attrs}
+{items}
 
 fn main() {{
+    // Stdout allocates its buffer on first use, which shouldn't be counted
+    let _ = std::io::Write::flush(&mut std::io::stdout());
     __STAT_ALLOC.reset();
 
     {{
         {code}
     }};
 
-    __STAT_ALLOC.print_stats();
+    __STAT_ALLOC.finish();
 }}
 
 // CODE BELOW IS ALLOC STAT BOILERPLATE
 
+/// Prints the stats since the previous phase (or the start) as `[name] …`.
+#[allow(dead_code)]
+fn alloc_phase(name: &str) {{
+    __STAT_ALLOC.phase(name);
+}}
+
 #[global_allocator]
-static __STAT_ALLOC: self::__stat_alloc::StatAlloc = self::__stat_alloc::StatAlloc {{
-    allocator: std::alloc::System,
-    num_allocs: std::sync::atomic::AtomicUsize::new(0),
-    num_deallocs: std::sync::atomic::AtomicUsize::new(0),
-    bytes_allocd: std::sync::atomic::AtomicUsize::new(0),
-    bytes_deallocd: std::sync::atomic::AtomicUsize::new(0),
-}};
+static __STAT_ALLOC: self::__stat_alloc::StatAlloc = self::__stat_alloc::StatAlloc::new();
 
 mod __stat_alloc {{
     use std::alloc::{{GlobalAlloc, System, Layout}};
-    use std::sync::atomic::{{AtomicUsize, Ordering::SeqCst}};
+    use std::fmt;
+    use std::sync::atomic::{{AtomicUsize, AtomicIsize, Ordering::SeqCst}};
 
     pub struct StatAlloc {{
-        pub allocator: System,
-        pub num_allocs: AtomicUsize,
-        pub num_deallocs: AtomicUsize,
-        pub bytes_allocd: AtomicUsize,
-        pub bytes_deallocd: AtomicUsize,
+        allocator: System,
+        /// Bytes currently allocated, negative if memory from before `reset` was freed.
+        live: AtomicIsize,
+        total: Counters,
+        phase: Counters,
+        num_phases: AtomicUsize,
+    }}
+
+    struct Counters {{
+        allocs: AtomicUsize,
+        reallocs: AtomicUsize,
+        deallocs: AtomicUsize,
+        bytes_allocd: AtomicUsize,
+        bytes_deallocd: AtomicUsize,
+        peak: AtomicIsize,
+        largest: AtomicUsize,
     }}
-    
+
+    /// A snapshot of `Counters`.
+    struct Stats {{
+        allocs: usize,
+        reallocs: usize,
+        deallocs: usize,
+        bytes_allocd: usize,
+        bytes_deallocd: usize,
+        peak: isize,
+        largest: usize,
+    }}
+
     impl StatAlloc {{
-        pub fn print_stats(&self) {{
-            let num_allocs = self.num_allocs.load(SeqCst);
-            let num_deallocs = self.num_deallocs.load(SeqCst);
-            let bytes_allocd = self.bytes_allocd.load(SeqCst);
-            let bytes_deallocd = self.bytes_deallocd.load(SeqCst);
-            println!("#allocs {{}} ({{}} bytes) :: #deallocs {{}} ({{}} bytes) :: #lost: {{}} ({{}} bytes)",
-                num_allocs, bytes_allocd,
-                num_deallocs, bytes_deallocd,
-                num_allocs as isize - num_deallocs as isize,
-                bytes_allocd as isize - bytes_deallocd as isize,
-            );
-        }}
-        
+        pub const fn new() -> Self {{
+            StatAlloc {{
+                allocator: System,
+                live: AtomicIsize::new(0),
+                total: Counters::new(),
+                phase: Counters::new(),
+                num_phases: AtomicUsize::new(0),
+            }}
+        }}
+
         pub fn reset(&self) {{
-            self.num_allocs.store(0, SeqCst);
-            self.num_deallocs.store(0, SeqCst);
+            self.live.store(0, SeqCst);
+            self.total.reset(0);
+            self.phase.reset(0);
+            self.num_phases.store(0, SeqCst);
+        }}
+
+        pub fn phase(&self, name: &str) {{
+            // Take the snapshot first, so printing it isn't counted
+            let stats = self.phase.stats();
+            println!("[{{}}] {{}}", name, stats);
+
+            self.num_phases.fetch_add(1, SeqCst);
+            self.phase.reset(self.live.load(SeqCst));
+        }}
+
+        pub fn finish(&self) {{
+            if self.num_phases.load(SeqCst) == 0 {{
+                println!("{{}}", self.total.stats());
+                return;
+            }}
+
+            let total = self.total.stats();
+
+            if !self.phase.stats().is_empty() {{
+                self.phase("rest");
+            }}
+
+            println!("[total] {{}}", total);
+        }}
+
+        fn count_alloc(&self, size: usize) {{
+            let live = self.live.fetch_add(size as isize, SeqCst) + size as isize;
+            self.total.count_alloc(size, live);
+            self.phase.count_alloc(size, live);
+        }}
+
+        fn count_dealloc(&self, size: usize) {{
+            self.live.fetch_sub(size as isize, SeqCst);
+            self.total.count_dealloc(size);
+            self.phase.count_dealloc(size);
+        }}
+
+        fn count_realloc(&self, old_size: usize, new_size: usize) {{
+            let delta = new_size as isize - old_size as isize;
+            let live = self.live.fetch_add(delta, SeqCst) + delta;
+            self.total.count_realloc(old_size, new_size, live);
+            self.phase.count_realloc(old_size, new_size, live);
+        }}
+    }}
+
+    impl Counters {{
+        const fn new() -> Self {{
+            Counters {{
+                allocs: AtomicUsize::new(0),
+                reallocs: AtomicUsize::new(0),
+                deallocs: AtomicUsize::new(0),
+                bytes_allocd: AtomicUsize::new(0),
+                bytes_deallocd: AtomicUsize::new(0),
+                peak: AtomicIsize::new(0),
+                largest: AtomicUsize::new(0),
+            }}
+        }}
+
+        fn reset(&self, live: isize) {{
+            self.allocs.store(0, SeqCst);
+            self.reallocs.store(0, SeqCst);
+            self.deallocs.store(0, SeqCst);
             self.bytes_allocd.store(0, SeqCst);
             self.bytes_deallocd.store(0, SeqCst);
+            self.peak.store(live, SeqCst);
+            self.largest.store(0, SeqCst);
         }}
-        
-        fn count_alloc(&self, size: usize) {{
-            self.num_allocs.fetch_add(1, SeqCst);
+
+        fn stats(&self) -> Stats {{
+            Stats {{
+                allocs: self.allocs.load(SeqCst),
+                reallocs: self.reallocs.load(SeqCst),
+                deallocs: self.deallocs.load(SeqCst),
+                bytes_allocd: self.bytes_allocd.load(SeqCst),
+                bytes_deallocd: self.bytes_deallocd.load(SeqCst),
+                peak: self.peak.load(SeqCst),
+                largest: self.largest.load(SeqCst),
+            }}
+        }}
+
+        fn count_alloc(&self, size: usize, live: isize) {{
+            self.allocs.fetch_add(1, SeqCst);
             self.bytes_allocd.fetch_add(size, SeqCst);
+            self.largest.fetch_max(size, SeqCst);
+            self.peak.fetch_max(live, SeqCst);
         }}
 
         fn count_dealloc(&self, size: usize) {{
-            self.num_deallocs.fetch_add(1, SeqCst);
+            self.deallocs.fetch_add(1, SeqCst);
             self.bytes_deallocd.fetch_add(size, SeqCst);
         }}
+
+        /// Only the difference in size counts as allocated or freed.
+        fn count_realloc(&self, old_size: usize, new_size: usize, live: isize) {{
+            self.reallocs.fetch_add(1, SeqCst);
+
+            if new_size > old_size {{
+                self.bytes_allocd.fetch_add(new_size - old_size, SeqCst);
+                self.largest.fetch_max(new_size, SeqCst);
+                self.peak.fetch_max(live, SeqCst);
+            }} else {{
+                self.bytes_deallocd.fetch_add(old_size - new_size, SeqCst);
+            }}
+        }}
+    }}
+
+    impl Stats {{
+        fn is_empty(&self) -> bool {{
+            self.allocs == 0 && self.reallocs == 0 && self.deallocs == 0
+        }}
     }}
-    
+
+    impl fmt::Display for Stats {{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {{
+            write!(f, "allocs: {{}} ({{}}), reallocs: {{}}, frees: {{}} ({{}}), leaked: {{}} ({{}}), peak: {{}}, largest: {{}}",
+                self.allocs, Bytes(self.bytes_allocd as isize),
+                self.reallocs,
+                self.deallocs, Bytes(self.bytes_deallocd as isize),
+                self.allocs as isize - self.deallocs as isize,
+                Bytes(self.bytes_allocd as isize - self.bytes_deallocd as isize),
+                Bytes(self.peak),
+                Bytes(self.largest as isize),
+            )
+        }}
+    }}
+
+    /// A byte count like `512 B`, `1.5 KiB` or `-3.0 MiB`.
+    struct Bytes(isize);
+
+    impl fmt::Display for Bytes {{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {{
+            let sign = if self.0 < 0 {{ "-" }} else {{ "" }};
+            let bytes = self.0.unsigned_abs();
+
+            match bytes {{
+                0..=1023 => write!(f, "{{}}{{}} B", sign, bytes),
+                1024..=1048575 => write!(f, "{{}}{{:.1}} KiB", sign, bytes as f64 / 1024.0),
+                _ => write!(f, "{{}}{{:.1}} MiB", sign, bytes as f64 / 1024.0 / 1024.0),
+            }}
+        }}
+    }}
+
     unsafe impl GlobalAlloc for StatAlloc {{
         unsafe fn alloc(&self, layout: Layout) -> *mut u8 {{
             self.count_alloc(layout.size());
             self.allocator.alloc(layout)
         }}
-    
+
         unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {{
             self.count_dealloc(layout.size());
             self.allocator.dealloc(ptr, layout)
         }}
-    
+
         unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {{
             self.count_alloc(layout.size());
             self.allocator.alloc_zeroed(layout)
         }}
-    
+
         unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {{
-            self.count_dealloc(layout.size());
-            self.count_alloc(new_size);
+            self.count_realloc(layout.size(), new_size);
             self.allocator.realloc(ptr, layout, new_size)
         }}
     }}
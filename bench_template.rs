@@ -0,0 +1,118 @@
+#![allow(unreachable_code)]
+{crate_attrs}
+
+fn main() {{
+    let results = [
+        {benches}
+    ];
+
+    __bench::report(&results);
+}}
+
+{alternatives}
+
+// CODE BELOW IS BENCHMARK BOILERPLATE
+
+mod __bench {{
+    use std::fmt;
+    use std::hint::black_box;
+    use std::time::{{Duration, Instant}};
+
+    const SAMPLES: usize = 50;
+    /// Time spent measuring each expression.
+    const BUDGET: Duration = Duration::from_millis(1000);
+
+    /// Nanoseconds per iteration.
+    pub struct Stats {{
+        median: f64,
+        min: f64,
+        max: f64,
+        iterations: u64,
+    }}
+
+    pub fn run<T>(mut f: impl FnMut() -> T) -> Stats {{
+        // Fast expressions are timed in batches, so the clock's resolution doesn't matter
+        let target = BUDGET / SAMPLES as u32;
+        let mut batch = 1u64;
+
+        while time(&mut f, batch) < target && batch < 1 << 32 {{
+            batch *= 2;
+        }}
+
+        let mut samples = Vec::with_capacity(SAMPLES);
+        let start = Instant::now();
+
+        while samples.len() < SAMPLES && (samples.is_empty() || start.elapsed() < BUDGET) {{
+            let elapsed = time(&mut f, batch);
+            samples.push(elapsed.as_nanos() as f64 / batch as f64);
+        }}
+
+        samples.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        Stats {{
+            median: samples[samples.len() / 2],
+            min: samples[0],
+            max: samples[samples.len() - 1],
+            iterations: batch * samples.len() as u64,
+        }}
+    }}
+
+    fn time<T>(f: &mut impl FnMut() -> T, iterations: u64) -> Duration {{
+        let start = Instant::now();
+
+        for _ in 0..iterations {{
+            black_box(f());
+        }}
+
+        start.elapsed()
+    }}
+
+    /// Prints all results in one line, e.g. `#1: median 1.2 µs (…) | #2: … | #1 is 1.50x faster`.
+    pub fn report(results: &[Stats]) {{
+        if results.len() == 1 {{
+            println!("{{}}", results[0]);
+            return;
+        }}
+
+        let mut line = results.iter()
+            .enumerate()
+            .map(|(i, stats)| format!("#{{}}: {{}}", i + 1, stats))
+            .collect::<Vec<_>>()
+            .join(" | ");
+
+        let mut by_speed = results.iter().enumerate().collect::<Vec<_>>();
+        by_speed.sort_by(|(_, a), (_, b)| a.median.partial_cmp(&b.median).unwrap());
+        let (fastest, first) = by_speed[0];
+        let (_, second) = by_speed[1];
+
+        if first.median > 0.0 {{
+            line += &format!(" | #{{}} is {{:.2}}x faster", fastest + 1, second.median / first.median);
+        }}
+
+        println!("{{}}", line);
+    }}
+
+    impl fmt::Display for Stats {{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {{
+            write!(f, "median {{}} (min {{}}, max {{}}, {{}} iterations)",
+                Time(self.median),
+                Time(self.min),
+                Time(self.max),
+                self.iterations,
+            )
+        }}
+    }}
+
+    struct Time(f64);
+
+    impl fmt::Display for Time {{
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {{
+            match self.0 {{
+                ns if ns < 1e3 => write!(f, "{{:.1}} ns", ns),
+                ns if ns < 1e6 => write!(f, "{{:.1}} µs", ns / 1e3),
+                ns if ns < 1e9 => write!(f, "{{:.1}} ms", ns / 1e6),
+                ns => write!(f, "{{:.2}} s", ns / 1e9),
+            }}
+        }}
+    }}
+}}
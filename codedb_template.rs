@@ -1,9 +0,0 @@
-r#"
-{fun}
-
-fn main() {{
-    println!("{{}}", {{
-        {call}
-    }});
-}}
-"#
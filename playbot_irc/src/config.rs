@@ -8,7 +8,15 @@ use slog::Logger;
 #[derive(Deserialize)]
 pub struct Config {
     #[serde(rename = "instance")]
-    pub instances: Vec<irc::client::data::Config>,
+    pub instances: Vec<Instance>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Instance {
+    #[serde(flatten)]
+    pub irc: irc::client::data::Config,
+    #[serde(default)]
+    pub playbot: playbot::Config,
 }
 
 impl Config {
@@ -16,6 +24,12 @@ impl Config {
         info!(l, "Loading config"; "path" => path.as_ref().display());
         let config = fs::read_to_string(path)?;
         let config = toml::de::from_str::<Self>(&config)?;
+
+        // Catch broken templates before connecting anywhere
+        for instance in &config.instances {
+            instance.playbot.playground.templates()?;
+        }
+
         Ok(config)
     }
 }
@@ -11,12 +11,12 @@ use chrono::{
 };
 use irc::client::prelude::{*, Config as IrcConfig};
 use failure::Error;
-use playbot::{Playbot, Message};
+use playbot::{Playbot, Message, Config as PlaybotConfig};
 use std::panic::catch_unwind;
 use slog::{Logger, Drain};
 
 mod config;
-use self::config::Config;
+use self::config::{Config, Instance};
 
 fn logger() -> Logger {
     let decorator = slog_term::TermDecorator::new().build();
@@ -48,17 +48,17 @@ fn main() {
     }
 }
 
-pub fn run_instance(config: IrcConfig, l: &Logger) {
-    let l = l.new(o!{"server" => config.server.clone()});
+pub fn run_instance(config: Instance, l: &Logger) {
+    let l = l.new(o!{"server" => config.irc.server.clone()});
     info!(l, "Starting instance");
 
     let sleep_dur = Duration::seconds(5).to_std().unwrap();
-    let server = config.server.as_ref().map(|x| &**x).unwrap_or("");
+    let server = config.irc.server.as_ref().map(|x| &**x).unwrap_or("");
 
     loop {   
         println!("{} Starting up", Utc::now());
 
-        match connect_and_handle(config.clone(), &l) {
+        match connect_and_handle(config.irc.clone(), config.playbot.clone(), &l) {
             Ok(()) => eprintln!("{}/[OK] Disconnected for an unknown reason", server),
             Err(e) => {
                 eprintln!("[{}/ERR] Disconnected", server);
@@ -77,12 +77,11 @@ pub fn run_instance(config: IrcConfig, l: &Logger) {
     }
 }
 
-pub fn connect_and_handle(config: IrcConfig, l: &Logger) -> Result<(), Error> {
+pub fn connect_and_handle(config: IrcConfig, playbot_config: PlaybotConfig, l: &Logger) -> Result<(), Error> {
     let l = l.clone();
-    //    let mut codedb = ::codedb::CodeDB::open_or_create("code_db.json")?;
+    let playbot = Arc::new(Playbot::new(playbot_config)?);
     let mut reactor = IrcReactor::new()?;
     let client = reactor.prepare_client_and_connect(config)?;
-    let playbot = Arc::new(Playbot::new());
 
     client.identify()?;
 
@@ -117,6 +116,7 @@ pub struct IrcMessage {
     source: Prefix,
     source_nickname: ArcStr,
     target: ArcStr,
+    is_channel: bool,
     client: IrcClient,
     current_nickname: ArcStr,
 }
@@ -179,6 +179,7 @@ impl IrcMessage {
             source: source.to_owned(),
             source_nickname: source_nickname.into(),
             target: target.into(),
+            is_channel: target.is_channel_name(),
             is_directly_addressed,
             current_nickname: current_nickname.to_string().into(),
         })
@@ -210,6 +211,14 @@ impl Message for IrcMessage {
         Ok(())
     }
 
+    fn channel(&self) -> Option<ArcStr> {
+        if self.is_channel {
+            Some(self.target.clone())
+        } else {
+            None
+        }
+    }
+
     fn source_nickname(&self) -> ArcStr {
         self.source_nickname.clone()
     }
@@ -0,0 +1,22 @@
+use crate::{PlaygroundClient, ExecuteRequest, ExecuteResponse, Error};
+use futures::prelude::*;
+
+/// Something that can compile and run code.
+///
+/// The playground itself is one backend (via `PlaygroundClient`),
+/// `LocalBackend` is another one that runs code on the bot's own machine.
+pub trait Backend: Send + Sync {
+    fn execute(&self, req: &ExecuteRequest) -> Result<ExecuteResponse, Error>;
+
+    fn async_execute(&self, req: &ExecuteRequest) -> Box<Future<Item = ExecuteResponse, Error = Error> + Send>;
+}
+
+impl Backend for PlaygroundClient {
+    fn execute(&self, req: &ExecuteRequest) -> Result<ExecuteResponse, Error> {
+        crate::execute(self, req)
+    }
+
+    fn async_execute(&self, req: &ExecuteRequest) -> Box<Future<Item = ExecuteResponse, Error = Error> + Send> {
+        Box::new(crate::async_execute(self, req))
+    }
+}
@@ -0,0 +1,232 @@
+use crate::client::Call;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Remembers successful playground responses, so identical requests
+/// (the same code with the same channel, mode, edition, crate type and tool)
+/// are answered without asking the playground again.
+#[derive(Clone, Debug)]
+pub struct Cache {
+    ttl: Duration,
+    /// Number of responses to keep, `0` disables the cache.
+    max_entries: usize,
+    /// Combined size of all cached requests and responses.
+    max_bytes: usize,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    entries: HashMap<Call, Entry>,
+    bytes: usize,
+}
+
+#[derive(Debug)]
+struct Entry {
+    body: String,
+    inserted: Instant,
+    last_used: Instant,
+}
+
+impl Cache {
+    pub fn new(ttl: Duration, max_entries: usize, max_bytes: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            max_bytes,
+            state: Arc::new(Mutex::new(State::default())),
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self::new(Duration::from_secs(0), 0, 0)
+    }
+
+    /// Returns the cached response body for the call, if it hasn't expired yet.
+    pub(crate) fn get(&self, call: &Call) -> Option<String> {
+        let mut state = self.state.lock().unwrap();
+
+        let expired = match state.entries.get_mut(call) {
+            Some(entry) if entry.inserted.elapsed() < self.ttl => {
+                entry.last_used = Instant::now();
+                return Some(entry.body.clone());
+            },
+            Some(_) => true,
+            None => false,
+        };
+
+        if expired {
+            state.remove(call);
+        }
+
+        None
+    }
+
+    pub(crate) fn insert(&self, call: &Call, body: String) {
+        let size = call.size() + body.len();
+
+        if self.max_entries == 0 || size > self.max_bytes || !is_success(&body) {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let ttl = self.ttl;
+
+        state.remove(call);
+        state.retain(|entry| entry.inserted.elapsed() < ttl);
+
+        while state.entries.len() >= self.max_entries || state.bytes + size > self.max_bytes {
+            state.remove_least_recently_used();
+        }
+
+        let now = Instant::now();
+
+        state.bytes += size;
+        state.entries.insert(call.clone(), Entry {
+            body,
+            inserted: now,
+            last_used: now,
+        });
+    }
+}
+
+/// Whether the response reports success, if it has a `success` field at all.
+///
+/// Failed builds and runs aren't cached, they may have failed
+/// because of a busy playground rather than because of the code.
+fn is_success(body: &str) -> bool {
+    #[derive(Deserialize)]
+    struct Outcome {
+        success: Option<bool>,
+    }
+
+    serde_json::from_str::<Outcome>(body).map_or(true, |outcome| outcome.success != Some(false))
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(10 * 60), 512, 16 * 1024 * 1024)
+    }
+}
+
+impl State {
+    fn remove(&mut self, call: &Call) {
+        if let Some(entry) = self.entries.remove(call) {
+            self.bytes -= call.size() + entry.body.len();
+        }
+    }
+
+    fn retain(&mut self, mut keep: impl FnMut(&Entry) -> bool) {
+        let bytes = &mut self.bytes;
+
+        self.entries.retain(|call, entry| {
+            let keep = keep(entry);
+
+            if !keep {
+                *bytes -= call.size() + entry.body.len();
+            }
+
+            keep
+        });
+    }
+
+    fn remove_least_recently_used(&mut self) {
+        let call = self.entries.iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(call, _)| call.clone());
+
+        if let Some(call) = call {
+            self.remove(&call);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PlaygroundClient;
+
+    fn call(code: &str) -> Call {
+        PlaygroundClient::new().unwrap().post("/execute", &code)
+    }
+
+    fn cache() -> Cache {
+        Cache::new(Duration::from_secs(60), 2, 1024)
+    }
+
+    #[test]
+    fn answers_identical_calls() {
+        let cache = cache();
+        cache.insert(&call("1"), r#"{"success":true,"stdout":"1"}"#.to_owned());
+
+        assert_eq!(cache.get(&call("1")), Some(r#"{"success":true,"stdout":"1"}"#.to_owned()));
+        assert_eq!(cache.get(&call("2")), None);
+        assert_eq!(cache.get(&PlaygroundClient::new().unwrap().get("/execute")), None);
+    }
+
+    #[test]
+    fn skips_failures() {
+        let cache = cache();
+        cache.insert(&call("1"), r#"{"success":false,"stderr":"timed out"}"#.to_owned());
+        cache.insert(&call("2"), r#"{"version":"1.0.0"}"#.to_owned());
+
+        assert_eq!(cache.get(&call("1")), None);
+        assert!(cache.get(&call("2")).is_some());
+    }
+
+    #[test]
+    fn expires_entries() {
+        let cache = Cache::new(Duration::from_secs(0), 2, 1024);
+        cache.insert(&call("1"), "{}".to_owned());
+
+        assert_eq!(cache.get(&call("1")), None);
+        assert_eq!(cache.state.lock().unwrap().bytes, 0);
+    }
+
+    #[test]
+    fn disabled_keeps_nothing() {
+        let cache = Cache::disabled();
+        cache.insert(&call("1"), "{}".to_owned());
+
+        assert_eq!(cache.get(&call("1")), None);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = cache();
+        cache.insert(&call("1"), "{}".to_owned());
+        cache.insert(&call("2"), "{}".to_owned());
+        std::thread::sleep(Duration::from_millis(1));
+        cache.get(&call("1"));
+        cache.insert(&call("3"), "{}".to_owned());
+
+        assert!(cache.get(&call("1")).is_some());
+        assert_eq!(cache.get(&call("2")), None);
+        assert!(cache.get(&call("3")).is_some());
+    }
+
+    #[test]
+    fn limits_size() {
+        let cache = cache();
+        cache.insert(&call("1"), "x".repeat(2000));
+        assert_eq!(cache.get(&call("1")), None);
+
+        cache.insert(&call("1"), "x".repeat(600));
+        cache.insert(&call("2"), "x".repeat(600));
+
+        assert_eq!(cache.get(&call("1")), None);
+        assert!(cache.get(&call("2")).is_some());
+        assert!(cache.state.lock().unwrap().bytes <= 1024);
+    }
+
+    #[test]
+    fn replaces_entries() {
+        let cache = cache();
+        cache.insert(&call("1"), "{}".to_owned());
+        cache.insert(&call("1"), "[]".to_owned());
+
+        assert_eq!(cache.get(&call("1")), Some("[]".to_owned()));
+        assert_eq!(cache.state.lock().unwrap().bytes, call("1").size() + 2);
+    }
+}
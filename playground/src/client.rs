@@ -0,0 +1,321 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::{Error, RetryPolicy, CircuitBreaker, Cache};
+use reqwest::{Client, RequestBuilder, Method};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::r#async as async_reqwest;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use futures::prelude::*;
+use futures::future::{self, Either, Loop};
+use tokio_timer::Delay;
+
+pub const DEFAULT_BASE_URL: &str = "https://play.rust-lang.org";
+
+/// Handle to a playground deployment.
+///
+/// All calls into the playground go through a `PlaygroundClient`,
+/// so pointing the bot at a self-hosted playground (or a local stand-in)
+/// only requires a different base URL.
+#[derive(Clone)]
+pub struct PlaygroundClient {
+    base_url: String,
+    http: Client,
+    async_http: async_reqwest::Client,
+    retry: RetryPolicy,
+    breaker: CircuitBreaker,
+    cache: Cache,
+}
+
+impl PlaygroundClient {
+    /// Creates a client for the official playground with default settings.
+    pub fn new() -> Result<Self, Error> {
+        Self::builder().build()
+    }
+
+    pub fn builder() -> Builder {
+        Builder::new()
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Joins `path` onto the base URL, e.g. `url("/execute")`.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path.trim_start_matches('/'))
+    }
+
+    pub fn http(&self) -> &Client {
+        &self.http
+    }
+
+    pub fn async_http(&self) -> &async_reqwest::Client {
+        &self.async_http
+    }
+}
+
+/// A request to the playground that can be sent (and retried) any number of times.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Call {
+    method: Method,
+    url: String,
+    body: Option<Vec<u8>>,
+    /// Bypass the cache in both directions.
+    no_cache: bool,
+}
+
+impl PlaygroundClient {
+    pub(crate) fn get(&self, path: &str) -> Call {
+        Call {
+            method: Method::GET,
+            url: self.url(path),
+            body: None,
+            no_cache: false,
+        }
+    }
+
+    pub(crate) fn post<B: Serialize>(&self, path: &str, body: &B) -> Call {
+        Call {
+            method: Method::POST,
+            url: self.url(path),
+            body: Some(serde_json::to_vec(body).expect("playground requests always serialize")),
+            no_cache: false,
+        }
+    }
+}
+
+impl Call {
+    pub(crate) fn set_no_cache(&mut self, state: bool) {
+        self.no_cache = state;
+    }
+
+    /// GET requests are safe to retry even if they might have reached the playground.
+    fn is_idempotent(&self) -> bool {
+        self.method == Method::GET
+    }
+
+    /// Approximate memory used by the call.
+    pub(crate) fn size(&self) -> usize {
+        self.url.len() + self.body.as_ref().map_or(0, |body| body.len())
+    }
+
+    fn request(&self, http: &Client) -> RequestBuilder {
+        let req = http.request(self.method.clone(), &self.url);
+
+        match &self.body {
+            Some(body) => req.header(CONTENT_TYPE, "application/json").body(body.clone()),
+            None => req,
+        }
+    }
+
+    fn async_request(&self, http: &async_reqwest::Client) -> async_reqwest::RequestBuilder {
+        let req = http.request(self.method.clone(), &self.url);
+
+        match &self.body {
+            Some(body) => req.header(CONTENT_TYPE, "application/json").body(body.clone()),
+            None => req,
+        }
+    }
+}
+
+/// Answers from the cache or sends the request once, and decodes the JSON response.
+///
+/// Failures aren't retried here, waiting between attempts would block the caller's thread.
+pub(crate) fn send_json<T: DeserializeOwned>(client: &PlaygroundClient, call: Call) -> Result<T, Error> {
+    if let Some(body) = cached(client, &call) {
+        return decode(&body);
+    }
+
+    client.breaker.check()?;
+
+    let result = send_once(call.request(&client.http));
+    client.breaker.record(&result);
+
+    let body = result?;
+    let value = decode(&body)?;
+
+    if !call.no_cache {
+        client.cache.insert(&call, body);
+    }
+
+    Ok(value)
+}
+
+/// Answers from the cache or sends the request, retrying transient failures,
+/// and decodes the JSON response.
+pub(crate) fn async_send_json<T: DeserializeOwned>(client: &PlaygroundClient, call: Call) -> impl Future<Item = T, Error = Error> {
+    if let Some(body) = cached(client, &call) {
+        return Either::A(future::result(decode(&body)));
+    }
+
+    let http = client.async_http.clone();
+    let retry = client.retry.clone();
+    let breaker = client.breaker.clone();
+    let cache = client.cache.clone();
+    let call = Arc::new(call);
+    let cached_call = call.clone();
+
+    let body = future::loop_fn(0, move |attempt| {
+        let retry = retry.clone();
+        let breaker = breaker.clone();
+        let call = call.clone();
+
+        // Requests turned away by an open circuit never reach the playground, so they aren't recorded
+        if let Err(err) = breaker.check() {
+            return Either::A(future::err(err));
+        }
+
+        Either::B(async_send_once(call.async_request(&http)).then(move |result| {
+            breaker.record(&result);
+
+            let err = match result {
+                Ok(value) => return Either::A(future::ok(Loop::Break(value))),
+                Err(err) => err,
+            };
+
+            match retry.backoff(attempt, &err, call.is_idempotent()) {
+                Some(backoff) => Either::B(
+                    Delay::new(Instant::now() + backoff)
+                    .then(move |_| Ok::<_, Error>(Loop::Continue(attempt + 1)))
+                ),
+                None => Either::A(future::err(err)),
+            }
+        }))
+    });
+
+    Either::B(body.and_then(move |body| {
+        let value = decode(&body)?;
+
+        if !cached_call.no_cache {
+            cache.insert(&cached_call, body);
+        }
+
+        Ok(value)
+    }))
+}
+
+fn cached(client: &PlaygroundClient, call: &Call) -> Option<String> {
+    if call.no_cache {
+        return None;
+    }
+
+    client.cache.get(call)
+}
+
+/// Sends the request once and returns the body of a successful response.
+fn send_once(req: RequestBuilder) -> Result<String, Error> {
+    let mut resp = req.send()?;
+    let body = resp.text()?;
+
+    Error::check(resp.status(), resp.headers(), &body)?;
+
+    Ok(body)
+}
+
+fn async_send_once(req: async_reqwest::RequestBuilder) -> impl Future<Item = String, Error = Error> {
+    req
+    .send()
+    .and_then(|resp| {
+        let status = resp.status();
+        let headers = resp.headers().clone();
+
+        resp.into_body()
+        .concat2()
+        .map(move |body| (status, headers, body))
+    })
+    .map_err(Error::from)
+    .and_then(|(status, headers, body)| {
+        let body = String::from_utf8_lossy(&body).into_owned();
+        Error::check(status, &headers, &body)?;
+
+        Ok(body)
+    })
+}
+
+fn decode<T: DeserializeOwned>(body: &str) -> Result<T, Error> {
+    serde_json::from_str(body).map_err(Error::Decode)
+}
+
+pub struct Builder {
+    base_url: String,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    retry: RetryPolicy,
+    breaker: CircuitBreaker,
+    cache: Cache,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.into(),
+            timeout: Some(Duration::from_secs(30)),
+            connect_timeout: Some(Duration::from_secs(10)),
+            retry: RetryPolicy::default(),
+            breaker: CircuitBreaker::default(),
+            cache: Cache::default(),
+        }
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Total time a single request may take, `None` disables the timeout.
+    pub fn timeout(mut self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.timeout = timeout.into();
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.connect_timeout = timeout.into();
+        self
+    }
+
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    pub fn cache(mut self, cache: Cache) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    pub fn build(self) -> Result<PlaygroundClient, Error> {
+        let mut http = Client::builder()
+            .timeout(self.timeout);
+        let mut async_http = async_reqwest::Client::builder();
+
+        if let Some(timeout) = self.timeout {
+            async_http = async_http.timeout(timeout);
+        }
+
+        if let Some(timeout) = self.connect_timeout {
+            http = http.connect_timeout(timeout);
+            async_http = async_http.connect_timeout(timeout);
+        }
+
+        Ok(PlaygroundClient {
+            base_url: self.base_url.trim_end_matches('/').into(),
+            http: http.build()?,
+            async_http: async_http.build()?,
+            retry: self.retry,
+            breaker: self.breaker,
+            cache: self.cache,
+        })
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
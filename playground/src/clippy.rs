@@ -0,0 +1,63 @@
+use crate::{CrateType, PlaygroundClient, Error, Edition};
+use crate::client::{send_json, async_send_json};
+use std::borrow::Cow;
+use futures::prelude::*;
+
+pub fn clippy(client: &PlaygroundClient, req: &Request) -> Result<Response, Error> {
+    send_json(client, client.post("/clippy", req))
+}
+
+pub fn async_clippy(client: &PlaygroundClient, req: &Request) -> impl Future<Item = Response, Error = Error> {
+    async_send_json(client, client.post("/clippy", req))
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Request<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    edition: Option<Edition>,
+    crate_type: CrateType,
+    code: Cow<'a, str>,
+}
+
+impl<'a> Request<'a> {
+    pub fn new<S: Into<Cow<'a, str>>>(code: S) -> Self {
+        Self {
+            code: code.into(),
+            crate_type: CrateType::Bin,
+            edition: Some(Edition::default()),
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn set_code(&mut self, code: impl Into<Cow<'a, str>>) {
+        self.code = code.into();
+    }
+
+    pub fn edition(&self) -> Option<Edition> {
+        self.edition
+    }
+
+    pub fn set_edition(&mut self, edition: Option<Edition>) {
+        self.edition = edition;
+    }
+
+    pub fn crate_type(&self) -> CrateType {
+        self.crate_type
+    }
+
+    pub fn set_crate_type(&mut self, crate_type: CrateType) {
+        self.crate_type = crate_type;
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Response {
+    pub success: bool,
+    pub stdout: String,
+    /// Clippy's lints and any compiler errors.
+    pub stderr: String,
+}
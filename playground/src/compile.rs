@@ -0,0 +1,190 @@
+use crate::{Channel, CrateType, Mode, PlaygroundClient, Error, ExecuteRequest, Edition};
+use crate::client::{send_json, async_send_json};
+use std::borrow::Cow;
+use futures::prelude::*;
+
+pub fn compile(client: &PlaygroundClient, req: &Request) -> Result<Response, Error> {
+    send_json(client, client.post("/compile", req))
+}
+
+pub fn async_compile(client: &PlaygroundClient, req: &Request) -> impl Future<Item = Response, Error = Error> {
+    async_send_json(client, client.post("/compile", req))
+}
+
+#[derive(Serialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Target {
+    Asm,
+    LlvmIr,
+    Mir,
+    Hir,
+    Wasm,
+}
+
+impl Target {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Target::Asm => "asm",
+            Target::LlvmIr => "llvm-ir",
+            Target::Mir => "mir",
+            Target::Hir => "hir",
+            Target::Wasm => "wasm",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AssemblyFlavor {
+    Att,
+    Intel,
+}
+
+#[derive(Serialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DemangleAssembly {
+    Demangle,
+    Mangle,
+}
+
+#[derive(Serialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProcessAssembly {
+    Filter,
+    Raw,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Request<'a> {
+    target: Target,
+    assembly_flavor: AssemblyFlavor,
+    demangle_assembly: DemangleAssembly,
+    process_assembly: ProcessAssembly,
+    channel: Channel,
+    mode: Mode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    edition: Option<Edition>,
+    crate_type: CrateType,
+    tests: bool,
+    backtrace: bool,
+    code: Cow<'a, str>,
+}
+
+impl<'a> Request<'a> {
+    pub fn new<S: Into<Cow<'a, str>>>(code: S, target: Target) -> Self {
+        Self {
+            target,
+            assembly_flavor: AssemblyFlavor::Att,
+            demangle_assembly: DemangleAssembly::Demangle,
+            process_assembly: ProcessAssembly::Filter,
+            code: code.into(),
+            channel: Channel::Stable,
+            crate_type: CrateType::Bin,
+            mode: Mode::Debug,
+            edition: Some(Edition::default()),
+            backtrace: false,
+            tests: false,
+        }
+    }
+
+    /// Creates a compile request with the same settings as an execute request.
+    pub fn from_execute(req: &ExecuteRequest<'a>, target: Target) -> Self {
+        let mut compile_req = Self::new(req.code().to_owned(), target);
+        compile_req.set_channel(req.channel());
+        compile_req.set_mode(req.mode());
+        compile_req.set_edition(req.edition());
+        compile_req.set_crate_type(req.crate_type());
+        compile_req.set_tests(req.tests());
+        compile_req
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn set_code(&mut self, code: impl Into<Cow<'a, str>>) {
+        self.code = code.into();
+    }
+
+    pub fn target(&self) -> Target {
+        self.target
+    }
+
+    pub fn set_target(&mut self, target: Target) {
+        self.target = target;
+    }
+
+    pub fn assembly_flavor(&self) -> AssemblyFlavor {
+        self.assembly_flavor
+    }
+
+    pub fn set_assembly_flavor(&mut self, assembly_flavor: AssemblyFlavor) {
+        self.assembly_flavor = assembly_flavor;
+    }
+
+    pub fn demangle_assembly(&self) -> DemangleAssembly {
+        self.demangle_assembly
+    }
+
+    pub fn set_demangle_assembly(&mut self, demangle_assembly: DemangleAssembly) {
+        self.demangle_assembly = demangle_assembly;
+    }
+
+    pub fn process_assembly(&self) -> ProcessAssembly {
+        self.process_assembly
+    }
+
+    pub fn set_process_assembly(&mut self, process_assembly: ProcessAssembly) {
+        self.process_assembly = process_assembly;
+    }
+
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    pub fn set_channel(&mut self, channel: Channel) {
+        self.channel = channel;
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    pub fn edition(&self) -> Option<Edition> {
+        self.edition
+    }
+
+    pub fn set_edition(&mut self, edition: Option<Edition>) {
+        self.edition = edition;
+    }
+
+    pub fn crate_type(&self) -> CrateType {
+        self.crate_type
+    }
+
+    pub fn set_crate_type(&mut self, crate_type: CrateType) {
+        self.crate_type = crate_type;
+    }
+
+    pub fn tests(&self) -> bool {
+        self.tests
+    }
+
+    pub fn set_tests(&mut self, state: bool) {
+        self.tests = state;
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Response {
+    pub success: bool,
+    /// The generated listing in the requested target format.
+    pub code: String,
+    pub stdout: String,
+    pub stderr: String,
+}
@@ -0,0 +1,60 @@
+use crate::{PlaygroundClient, Error};
+use crate::client::{send_json, async_send_json};
+use futures::prelude::*;
+
+pub fn crates(client: &PlaygroundClient) -> Result<Crates, Error> {
+    send_json(client, client.get("/meta/crates"))
+}
+
+pub fn async_crates(client: &PlaygroundClient) -> impl Future<Item = Crates, Error = Error> {
+    async_send_json(client, client.get("/meta/crates"))
+}
+
+/// The crates that are available to code run in the playground.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Crates {
+    crates: Vec<Crate>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Crate {
+    name: String,
+    version: String,
+    id: String,
+}
+
+impl Crates {
+    pub fn crates(&self) -> &[Crate] {
+        &self.crates
+    }
+
+    /// Looks up a crate by name, treating `-` and `_` as equal.
+    pub fn find(&self, name: &str) -> Option<&Crate> {
+        let name = normalize(name);
+        self.crates.iter().find(|krate| normalize(&krate.name) == name)
+    }
+
+    /// Crates whose name contains `query`, treating `-` and `_` as equal.
+    pub fn search<'a>(&'a self, query: &str) -> impl Iterator<Item = &'a Crate> {
+        let query = normalize(query);
+        self.crates.iter().filter(move |krate| normalize(&krate.name).contains(&query))
+    }
+}
+
+impl Crate {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.trim().to_lowercase().replace('-', "_")
+}
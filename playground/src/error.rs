@@ -0,0 +1,116 @@
+use std::fmt;
+use std::io;
+use std::time::Duration;
+use std::error::Error as StdError;
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+
+/// Everything that can go wrong while talking to the playground.
+///
+/// The `Display` impl is phrased so it can be shown to users as-is.
+#[derive(Debug)]
+pub enum Error {
+    /// The playground could not be reached.
+    Network(reqwest::Error),
+    /// The playground did not respond in time.
+    Timeout,
+    /// Too many requests to the playground failed recently,
+    /// so it is assumed to be down for now.
+    Unavailable,
+    /// The playground refused the request because of too many requests.
+    RateLimited { retry_after: Option<Duration> },
+    /// The playground responded with an unexpected HTTP status.
+    Status { status: StatusCode, body: String },
+    /// The response could not be decoded.
+    Decode(serde_json::Error),
+    /// The local sandbox could not be set up or run.
+    Sandbox(io::Error),
+    /// The playground itself failed to process the request,
+    /// e.g. because the build timed out or the container crashed.
+    Compiler(String),
+}
+
+impl Error {
+    /// Fails if a raw playground response signals an error instead of carrying a result.
+    pub(crate) fn check(status: StatusCode, headers: &HeaderMap, body: &str) -> Result<(), Error> {
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = headers.get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse().ok())
+                .map(Duration::from_secs);
+
+            return Err(Error::RateLimited { retry_after });
+        }
+
+        if !status.is_success() {
+            return Err(Error::Status {
+                status,
+                body: body.to_owned(),
+            });
+        }
+
+        // A successful response may still only carry an error instead of a result
+        if let Ok(ErrorBody { error }) = serde_json::from_str::<ErrorBody>(body) {
+            return Err(Error::Compiler(error));
+        }
+
+        Ok(())
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        match *self {
+            Error::Timeout => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Network(_) => write!(f, "could not reach the playground"),
+            Error::Timeout => write!(f, "the playground took too long to respond"),
+            Error::Unavailable => write!(f, "the playground is currently unavailable"),
+            Error::RateLimited { retry_after: Some(retry_after) } =>
+                write!(f, "the playground is rate limited, try again in {}s", retry_after.as_secs()),
+            Error::RateLimited { retry_after: None } =>
+                write!(f, "the playground is rate limited, try again later"),
+            Error::Status { status, .. } => write!(f, "the playground responded with {}", status),
+            Error::Decode(_) => write!(f, "the playground sent an unexpected response"),
+            Error::Sandbox(_) => write!(f, "the sandbox failed to run the code"),
+            Error::Compiler(error) => write!(f, "the playground failed: {}", error.trim()),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(StdError + 'static)> {
+        match self {
+            Error::Network(err) => Some(err),
+            Error::Decode(err) => Some(err),
+            Error::Sandbox(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            return Error::Timeout;
+        }
+
+        Error::Network(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Sandbox(err)
+    }
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+}
@@ -1,43 +1,36 @@
-use crate::{Channel, CrateType, Mode};
+use crate::{Channel, CrateType, Mode, PlaygroundClient, Error, Edition};
+use crate::client::{Call, send_json, async_send_json};
 use std::borrow::Cow;
-use reqwest::{Client, Error};
-use reqwest::r#async as async_reqwest;
 use futures::prelude::*;
 
-pub fn execute(client: &Client, req: &Request) -> Result<Response, Error> {
-    let resp = client
-        .post("https://play.rust-lang.org/execute")
-        .json(req)
-        .send()?
-        .error_for_status()?
-        .json()?;
-    
-    Ok(resp)
+pub fn execute(client: &PlaygroundClient, req: &Request) -> Result<Response, Error> {
+    send_json(client, call(client, req))
 }
 
-pub fn async_execute(req: &Request) -> impl Future<Item = Response, Error = Error> {
-    let url = "https://play.rust-lang.org/execute";
-    let client = async_reqwest::Client::new();
+pub fn async_execute(client: &PlaygroundClient, req: &Request) -> impl Future<Item = Response, Error = Error> {
+    async_send_json(client, call(client, req))
+}
 
-    client
-    .post(url)
-    .json(req)
-    .send()
-    .and_then(|resp| resp.error_for_status())
-    .and_then(|mut resp| resp.json())
+fn call(client: &PlaygroundClient, req: &Request) -> Call {
+    let mut call = client.post("/execute", req);
+    call.set_no_cache(req.no_cache());
+    call
 }
 
-#[derive(Serialize,Debug)]
+#[derive(Serialize,Debug,Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Request<'a> {
     channel: Channel,
     mode: Mode,
     #[serde(skip_serializing_if = "Option::is_none")]
-    edition: Option<String>,
+    edition: Option<Edition>,
     crate_type: CrateType,
     tests: bool,
     backtrace: bool,
     code: Cow<'a, str>,
+    /// Not part of the request, see `set_no_cache`.
+    #[serde(skip)]
+    no_cache: bool,
 }
 
 impl<'a> Request<'a> {
@@ -47,13 +40,14 @@ impl<'a> Request<'a> {
             channel: Channel::Stable,
             crate_type: CrateType::Bin,
             mode: Mode::Debug,
-            edition: Some("2018".into()),
+            edition: Some(Edition::default()),
             backtrace: false,
             tests: false,
+            no_cache: false,
         }
     }
 
-    pub fn new_with<S: Into<Cow<'a, str>>>(code: S, channel: Channel, mode: Mode, edition: Option<String>, crate_type: CrateType) -> Self {
+    pub fn new_with<S: Into<Cow<'a, str>>>(code: S, channel: Channel, mode: Mode, edition: Option<Edition>, crate_type: CrateType) -> Self {
         Self {
             code: code.into(),
             channel,
@@ -62,6 +56,20 @@ impl<'a> Request<'a> {
             edition,
             backtrace: false,
             tests: false,
+            no_cache: false,
+        }
+    }
+
+    pub fn into_owned(self) -> Request<'static> {
+        Request {
+            code: Cow::Owned(self.code.into_owned()),
+            channel: self.channel,
+            crate_type: self.crate_type,
+            mode: self.mode,
+            edition: self.edition,
+            backtrace: self.backtrace,
+            tests: self.tests,
+            no_cache: self.no_cache,
         }
     }
 
@@ -81,6 +89,14 @@ impl<'a> Request<'a> {
         self.backtrace
     }
 
+    pub fn tests(&self) -> bool {
+        self.tests
+    }
+
+    pub fn set_tests(&mut self, state: bool) {
+        self.tests = state;
+    }
+
     pub fn set_channel(&mut self, channel: Channel) {
         self.channel = channel;
     }
@@ -97,11 +113,11 @@ impl<'a> Request<'a> {
         self.backtrace = state;
     }
 
-    pub fn edition(self) -> Option<String> {
+    pub fn edition(&self) -> Option<Edition> {
         self.edition
     }
 
-    pub fn set_edition(&mut self, edition: Option<String>) {
+    pub fn set_edition(&mut self, edition: Option<Edition>) {
         self.edition = edition;
     }
 
@@ -112,6 +128,16 @@ impl<'a> Request<'a> {
     pub fn set_crate_type(&mut self, crate_type: CrateType) {
         self.crate_type = crate_type;
     }
+
+    pub fn no_cache(&self) -> bool {
+        self.no_cache
+    }
+
+    /// Always runs the code, e.g. because its output depends on timing,
+    /// and doesn't remember the result either.
+    pub fn set_no_cache(&mut self, state: bool) {
+        self.no_cache = state;
+    }
 }
 
 #[derive(Deserialize,Debug)]
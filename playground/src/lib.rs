@@ -1,5 +1,24 @@
+//! Client for the Rust playground.
+//!
+//! Most calls come in two variants: the blocking one (e.g. `execute`) sends the request once
+//! and never retries, so it can't stall the calling thread on backoff delays.
+//! The `async_*` one (e.g. `async_execute`) retries transient failures according to the client's `RetryPolicy`.
+//! Both answer from the cache and are turned away while the circuit breaker is open.
+
 #[macro_use] extern crate serde_derive;
 
+mod error;
+pub use self::error::Error;
+
+mod retry;
+pub use self::retry::{RetryPolicy, CircuitBreaker};
+
+mod cache;
+pub use self::cache::Cache;
+
+mod client;
+pub use self::client::{PlaygroundClient, Builder as PlaygroundClientBuilder, DEFAULT_BASE_URL};
+
 pub mod execute;
 pub use self::execute::{
     execute,
@@ -8,6 +27,57 @@ pub use self::execute::{
     Response as ExecuteResponse,
 };
 
+pub mod compile;
+pub use self::compile::{
+    compile,
+    async_compile,
+    Request as CompileRequest,
+    Response as CompileResponse,
+    Target as CompileTarget,
+    AssemblyFlavor,
+};
+
+pub mod clippy;
+pub use self::clippy::{
+    clippy,
+    async_clippy,
+    Request as ClippyRequest,
+    Response as ClippyResponse,
+};
+
+pub mod format;
+pub use self::format::{
+    format,
+    async_format,
+    Request as FormatRequest,
+    Response as FormatResponse,
+};
+
+pub mod miri;
+pub use self::miri::{
+    miri,
+    async_miri,
+    Request as MiriRequest,
+    Response as MiriResponse,
+};
+
+pub mod macro_expansion;
+pub use self::macro_expansion::{
+    macro_expansion,
+    async_macro_expansion,
+    Request as MacroExpansionRequest,
+    Response as MacroExpansionResponse,
+};
+
+pub mod crates;
+pub use self::crates::{Crates, Crate, crates, async_crates};
+
+mod backend;
+pub use self::backend::Backend;
+
+pub mod local;
+pub use self::local::{LocalBackend, Sandbox, Limits};
+
 mod version;
 pub use self::version::{Version, version, async_version};
 
@@ -37,6 +107,15 @@ pub enum CrateType {
     Lib,
 }
 
+impl CrateType {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            CrateType::Bin => "bin",
+            CrateType::Lib => "lib",
+        }
+    }
+}
+
 #[derive(Serialize,Debug,Copy,Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum Channel {
@@ -55,14 +134,45 @@ impl Channel {
     }
 }
 
-#[derive(Deserialize)]
-pub struct Crates {
-    crates: Vec<Crate>,
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Edition {
+    #[serde(rename = "2015")]
+    E2015,
+    #[serde(rename = "2018")]
+    E2018,
+    #[serde(rename = "2021")]
+    E2021,
+    #[serde(rename = "2024")]
+    E2024,
+}
+
+impl Edition {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Edition::E2015 => "2015",
+            Edition::E2018 => "2018",
+            Edition::E2021 => "2021",
+            Edition::E2024 => "2024",
+        }
+    }
+}
+
+impl Default for Edition {
+    fn default() -> Self {
+        Edition::E2021
+    }
 }
 
-#[derive(Deserialize)]
-pub struct Crate {
-    name: String,
-    version: String,
-    id: String,
+impl std::str::FromStr for Edition {
+    type Err = String;
+
+    fn from_str(edition: &str) -> Result<Self, Self::Err> {
+        match edition {
+            "2015" => Ok(Edition::E2015),
+            "2018" => Ok(Edition::E2018),
+            "2021" => Ok(Edition::E2021),
+            "2024" => Ok(Edition::E2024),
+            _ => Err(format!("unknown edition '{}'", edition)),
+        }
+    }
 }
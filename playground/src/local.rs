@@ -0,0 +1,301 @@
+use crate::{Backend, CrateType, Mode, ExecuteRequest, ExecuteResponse, Error};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio, Child};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use futures::prelude::*;
+use futures::sync::oneshot;
+
+/// Compiles and runs code on the local machine, either as a plain
+/// subprocess or inside a docker container.
+///
+/// The toolchain is selected via rustup (`rustc +nightly`), so every
+/// channel that should be usable has to be installed on the host or in the image.
+#[derive(Clone, Debug)]
+pub struct LocalBackend {
+    sandbox: Sandbox,
+    limits: Limits,
+}
+
+#[derive(Clone, Debug)]
+pub enum Sandbox {
+    /// Run `rustc` and the program as subprocesses of the bot,
+    /// restricted via `ulimit` and `timeout`.
+    ///
+    /// This does not isolate the program in any way, it can read and write
+    /// whatever the bot can and access the network. Only use it if the bot
+    /// itself already runs in a sandbox.
+    Process,
+    /// Run everything inside a network-less docker container.
+    Docker { image: String },
+}
+
+#[derive(Clone, Debug)]
+pub struct Limits {
+    /// CPU time `rustc` and the program may use, each.
+    pub cpu_secs: u64,
+    /// Virtual memory `rustc` and the program may use, each.
+    pub memory_bytes: u64,
+    /// Wall clock time for compiling and running the program.
+    pub timeout: Duration,
+    /// Output beyond this many bytes per stream is discarded.
+    pub max_output_bytes: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            cpu_secs: 10,
+            memory_bytes: 512 * 1024 * 1024,
+            timeout: Duration::from_secs(30),
+            max_output_bytes: 64 * 1024,
+        }
+    }
+}
+
+impl LocalBackend {
+    pub fn new(sandbox: Sandbox, limits: Limits) -> Self {
+        Self { sandbox, limits }
+    }
+
+    pub fn sandbox(&self) -> &Sandbox {
+        &self.sandbox
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Shell script that compiles `main.rs` in the current directory to `output` and runs the result.
+    fn script(&self, req: &ExecuteRequest, output: &str) -> String {
+        let mut rustc = format!("rustc +{} --crate-name playground --crate-type {}",
+            req.channel().as_str(),
+            req.crate_type().as_str(),
+        );
+
+        if let Some(edition) = req.edition() {
+            rustc += &format!(" --edition {}", edition.as_str());
+        }
+
+        if let Mode::Release = req.mode() {
+            rustc += " -C opt-level=3";
+        }
+
+        if req.tests() {
+            rustc += " --test";
+        }
+
+        rustc += &format!(" -o {} main.rs", output);
+
+        let is_runnable = req.tests() || match req.crate_type() {
+            CrateType::Bin => true,
+            CrateType::Lib => false,
+        };
+
+        // Compiling can take unbounded time and memory too, e.g. through const evaluation
+        let ulimit = format!("ulimit -t {cpu}; ulimit -v {memory}",
+            cpu = self.limits.cpu_secs,
+            memory = self.limits.memory_bytes / 1024,
+        );
+
+        if !is_runnable {
+            return format!("{}; exec {}", ulimit, rustc);
+        }
+
+        format!("{ulimit}; {rustc} || exit $?; RUST_BACKTRACE={backtrace} exec {output}",
+            ulimit = ulimit,
+            rustc = rustc,
+            output = output,
+            backtrace = if req.backtrace() { 1 } else { 0 },
+        )
+    }
+
+    fn command(&self, req: &ExecuteRequest, dir: &Path, name: &str) -> Command {
+        let timeout = self.limits.timeout.as_secs().max(1).to_string();
+
+        match &self.sandbox {
+            Sandbox::Process => {
+                let script = self.script(req, "./playground");
+                // A new session, so everything the program starts can be killed as one process group
+                let mut command = Command::new("setsid");
+                command
+                    .args(&["timeout", "-s", "KILL", timeout.as_str(), "sh", "-c", script.as_str()])
+                    .current_dir(dir);
+                command
+            },
+            Sandbox::Docker { image } => {
+                // The code is mounted read-only, so the binary goes to the container's /tmp
+                let script = self.script(req, "/tmp/playground");
+                let memory = self.limits.memory_bytes.to_string();
+                let mut command = Command::new("docker");
+                command
+                    .args(&["run", "--rm", "--network", "none", "--pids-limit", "128", "--cpus", "1"])
+                    .args(&["--memory", memory.as_str(), "--memory-swap", memory.as_str()])
+                    .args(&["--name", name, "--workdir", "/playground"])
+                    .arg(format!("--volume={}:/playground:ro", dir.display()))
+                    .arg(image)
+                    .args(&["timeout", "-s", "KILL", timeout.as_str(), "sh", "-c", script.as_str()]);
+                command
+            },
+        }
+    }
+
+    fn run(&self, req: &ExecuteRequest) -> Result<ExecuteResponse, Error> {
+        let dir = TempDir::new()?;
+        fs::write(dir.path().join("main.rs"), req.code())?;
+
+        let mut child = self.command(req, dir.path(), dir.name())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let max_output_bytes = self.limits.max_output_bytes;
+        let stdout = child.stdout.take().map(|stdout| read_capped(stdout, max_output_bytes));
+        let stderr = child.stderr.take().map(|stderr| read_capped(stderr, max_output_bytes));
+
+        let started = Instant::now();
+        let success = self.wait(&mut child, dir.name());
+
+        // Leftover background processes would keep the pipes open and the readers waiting forever
+        if let Sandbox::Process = self.sandbox {
+            kill_process_group(&child);
+        }
+
+        let success = success?;
+
+        if started.elapsed() >= self.limits.timeout {
+            return Err(Error::Compiler(format!("execution timed out after {}s", self.limits.timeout.as_secs())));
+        }
+
+        let stdout = stdout.and_then(|stdout| stdout.join().ok()).unwrap_or_default();
+        let stderr = stderr.and_then(|stderr| stderr.join().ok()).unwrap_or_default();
+
+        Ok(ExecuteResponse {
+            success,
+            stdout,
+            stderr,
+        })
+    }
+
+    /// Waits for the child, killing it if `timeout` did not manage to.
+    fn wait(&self, child: &mut Child, name: &str) -> Result<bool, Error> {
+        let grace = Duration::from_secs(5);
+        let deadline = Instant::now() + self.limits.timeout + grace;
+
+        loop {
+            if let Some(status) = child.try_wait()? {
+                return Ok(status.success());
+            }
+
+            if Instant::now() >= deadline {
+                if let Sandbox::Docker { .. } = self.sandbox {
+                    Command::new("docker").args(&["kill", name]).output().ok();
+                }
+
+                child.kill().ok();
+                child.wait()?;
+                return Ok(false);
+            }
+
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
+
+impl Backend for LocalBackend {
+    fn execute(&self, req: &ExecuteRequest) -> Result<ExecuteResponse, Error> {
+        self.run(req)
+    }
+
+    fn async_execute(&self, req: &ExecuteRequest) -> Box<Future<Item = ExecuteResponse, Error = Error> + Send> {
+        let (tx, rx) = oneshot::channel();
+        let backend = self.clone();
+        let req = req.clone().into_owned();
+
+        thread::spawn(move || {
+            tx.send(backend.run(&req)).ok();
+        });
+
+        Box::new(
+            rx
+            .map_err(|_| Error::Compiler("the local backend stopped unexpectedly".into()))
+            .and_then(|result| result)
+        )
+    }
+}
+
+/// Kills the process group led by `child`, i.e. everything it started that is still running.
+fn kill_process_group(child: &Child) {
+    Command::new("kill")
+        .args(&["-s", "KILL", "--", &format!("-{}", child.id())])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .ok();
+}
+
+/// Reads the stream to the end on a separate thread, keeping at most `max_bytes`.
+fn read_capped<R: Read + Send + 'static>(mut reader: R, max_bytes: usize) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        let mut buf = [0; 4096];
+        let mut truncated = false;
+
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            };
+
+            // Keep draining the pipe so the program doesn't block on a full pipe
+            let keep = n.min(max_bytes.saturating_sub(output.len()));
+            output.extend_from_slice(&buf[..keep]);
+            truncated |= keep < n;
+        }
+
+        let mut output = String::from_utf8_lossy(&output).into_owned();
+
+        if truncated {
+            output.push_str("\n[output truncated]");
+        }
+
+        output
+    })
+}
+
+/// A directory that is removed when dropped.
+struct TempDir {
+    path: PathBuf,
+    name: String,
+}
+
+impl TempDir {
+    fn new() -> io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let name = format!("playbot-{}-{}", std::process::id(), COUNTER.fetch_add(1, Ordering::SeqCst));
+        let path = std::env::temp_dir().join(&name);
+        fs::create_dir_all(&path)?;
+
+        Ok(Self { path, name })
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Unique name, also used as the name of the docker container.
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.path).ok();
+    }
+}
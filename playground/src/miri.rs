@@ -0,0 +1,54 @@
+use crate::{PlaygroundClient, Error, Edition};
+use crate::client::{send_json, async_send_json};
+use std::borrow::Cow;
+use futures::prelude::*;
+
+pub fn miri(client: &PlaygroundClient, req: &Request) -> Result<Response, Error> {
+    send_json(client, client.post("/miri", req))
+}
+
+pub fn async_miri(client: &PlaygroundClient, req: &Request) -> impl Future<Item = Response, Error = Error> {
+    async_send_json(client, client.post("/miri", req))
+}
+
+/// Runs the code under Miri, which always uses the nightly toolchain.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Request<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    edition: Option<Edition>,
+    code: Cow<'a, str>,
+}
+
+impl<'a> Request<'a> {
+    pub fn new<S: Into<Cow<'a, str>>>(code: S) -> Self {
+        Self {
+            code: code.into(),
+            edition: Some(Edition::default()),
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn set_code(&mut self, code: impl Into<Cow<'a, str>>) {
+        self.code = code.into();
+    }
+
+    pub fn edition(&self) -> Option<Edition> {
+        self.edition
+    }
+
+    pub fn set_edition(&mut self, edition: Option<Edition>) {
+        self.edition = edition;
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Response {
+    pub success: bool,
+    pub stdout: String,
+    /// Miri's diagnostics, e.g. reports of undefined behavior.
+    pub stderr: String,
+}
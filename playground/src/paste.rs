@@ -1,42 +1,31 @@
-use crate::{Channel, Mode};
-use reqwest::{Client, Error};
-use reqwest::r#async as async_reqwest;
+use crate::{Channel, Mode, PlaygroundClient, Error};
+use crate::client::{send_json, async_send_json};
 use futures::prelude::*;
 
-pub fn paste<S: AsRef<str>>(client: &Client, text: S, channel: Channel, mode: Mode) -> Result<String, Error> {
-    let gist_id = client
-        .post("https://play.rust-lang.org/meta/gist/")
-        .json(&Request::new(text.as_ref()))
-        .send()?
-        .error_for_status()?
-        .json::<Response>()?
-        .id;
+pub fn paste<S: AsRef<str>>(client: &PlaygroundClient, text: S, channel: Channel, mode: Mode) -> Result<String, Error> {
+    let call = client.post("/meta/gist/", &Request::new(text.as_ref()));
+    let gist_id = send_json::<Response>(client, call)?.id;
 
-    let url = format!("https://play.rust-lang.org/?gist={gist}&version={channel}&mode={mode}",
-        gist = gist_id,
-        channel = channel.as_str(),
-        mode = mode.as_str()
-    );
-
-    Ok(url)
+    Ok(share_url(client.base_url(), &gist_id, channel, mode))
 }
 
-pub fn async_paste(text: impl Into<String>, channel: Channel, mode: Mode) -> impl Future<Item = String, Error = Error> {
+pub fn async_paste(client: &PlaygroundClient, text: impl Into<String>, channel: Channel, mode: Mode) -> impl Future<Item = String, Error = Error> {
     let text = text.into();
-    let client = async_reqwest::Client::new();
-    let url = "https://play.rust-lang.org/meta/gist/";
-
-    client
-    .post(url)
-    .json(&Request::new(text.as_ref()))
-    .send()
-    .and_then(|resp| resp.error_for_status())
-    .and_then(|mut resp| resp.json::<Response>())
-    .map(move |gist| format!("https://play.rust-lang.org/?gist={gist_id}&version={channel}&mode={mode}",
-        gist_id = gist.id,
+    let base_url = client.base_url().to_owned();
+
+    let call = client.post("/meta/gist/", &Request::new(text.as_ref()));
+
+    async_send_json::<Response>(client, call)
+    .map(move |gist| share_url(&base_url, &gist.id, channel, mode))
+}
+
+fn share_url(base_url: &str, gist_id: &str, channel: Channel, mode: Mode) -> String {
+    format!("{base_url}/?gist={gist}&version={channel}&mode={mode}",
+        base_url = base_url,
+        gist = gist_id,
         channel = channel.as_str(),
         mode = mode.as_str()
-    ))
+    )
 }
 
 #[derive(Serialize)]
@@ -0,0 +1,294 @@
+use crate::Error;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use reqwest::StatusCode;
+
+/// How often and how patiently failed requests are retried.
+///
+/// Only the `async_*` functions retry, the blocking ones send each request once.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt, `0` disables retrying.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every further retry.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the delay before retrying after the given attempt failed,
+    /// or `None` if the request should not be retried.
+    pub(crate) fn backoff(&self, attempt: u32, err: &Error, idempotent: bool) -> Option<Duration> {
+        if attempt >= self.max_retries || !is_retryable(err, idempotent) {
+            return None;
+        }
+
+        if let Error::RateLimited { retry_after: Some(retry_after) } = *err {
+            return Some(retry_after).filter(|retry_after| *retry_after <= self.max_backoff);
+        }
+
+        let backoff = self.initial_backoff * 2u32.saturating_pow(attempt);
+
+        Some(backoff.min(self.max_backoff))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+        }
+    }
+}
+
+/// Stops sending requests to the playground for a while
+/// after too many consecutive transient failures.
+#[derive(Clone, Debug)]
+pub struct CircuitBreaker {
+    /// Consecutive failures after which the circuit opens, `0` disables the breaker.
+    threshold: u32,
+    cooldown: Duration,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    /// Whether a trial request was let through after the cooldown.
+    half_open: bool,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            state: Arc::new(Mutex::new(State::default())),
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self::new(0, Duration::from_secs(0))
+    }
+
+    /// Fails with `Error::Unavailable` while the circuit is open.
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+
+        match state.open_until {
+            Some(open_until) if now < open_until => Err(Error::Unavailable),
+            Some(_) => {
+                // Half open: let a single trial request through and keep rejecting the others
+                // until it finishes. Should it never report back, the next cooldown admits another one.
+                state.open_until = Some(now + self.cooldown);
+                state.half_open = true;
+                Ok(())
+            },
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn record<T>(&self, result: &Result<T, Error>) {
+        if self.threshold == 0 {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+
+        match result {
+            // Rejected by the breaker itself, says nothing about the playground
+            Err(Error::Unavailable) => {},
+            Err(err) if is_outage(err) => {
+                state.consecutive_failures += 1;
+
+                // A failed trial request opens the circuit again right away
+                if state.half_open || state.consecutive_failures >= self.threshold {
+                    state.open_until = Some(Instant::now() + self.cooldown);
+                    state.half_open = false;
+                }
+            },
+            _ => *state = State::default(),
+        }
+    }
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(5, Duration::from_secs(60))
+    }
+}
+
+/// Whether retrying might succeed.
+/// Requests that may have reached the playground are only retried if they are idempotent.
+fn is_retryable(err: &Error, idempotent: bool) -> bool {
+    match err {
+        Error::RateLimited { .. } => true,
+        Error::Status { status, .. } if *status == StatusCode::BAD_GATEWAY => true,
+        Error::Status { status, .. } if *status == StatusCode::SERVICE_UNAVAILABLE => true,
+        Error::Status { status, .. } if *status == StatusCode::GATEWAY_TIMEOUT => idempotent,
+        Error::Network(_) | Error::Timeout => idempotent,
+        _ => false,
+    }
+}
+
+/// Whether the error indicates that the playground is down, as opposed to e.g. bad input.
+fn is_outage(err: &Error) -> bool {
+    match err {
+        Error::Network(_) | Error::Timeout => true,
+        Error::Status { status, .. } => status.is_server_error(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker() -> CircuitBreaker {
+        CircuitBreaker::new(2, Duration::from_secs(60))
+    }
+
+    fn fail(breaker: &CircuitBreaker, err: Error) {
+        breaker.record(&Err::<(), _>(err));
+    }
+
+    fn succeed(breaker: &CircuitBreaker) {
+        breaker.record(&Ok(()));
+    }
+
+    fn is_rejected(breaker: &CircuitBreaker) -> bool {
+        match breaker.check() {
+            Err(Error::Unavailable) => true,
+            _ => false,
+        }
+    }
+
+    /// Lets the cooldown run out.
+    fn cool_down(breaker: &CircuitBreaker) {
+        breaker.state.lock().unwrap().open_until = Some(Instant::now());
+    }
+
+    #[test]
+    fn opens_after_consecutive_outages() {
+        let breaker = breaker();
+
+        fail(&breaker, Error::Timeout);
+        assert!(!is_rejected(&breaker));
+
+        fail(&breaker, Error::Timeout);
+        assert!(is_rejected(&breaker));
+    }
+
+    #[test]
+    fn success_resets_the_count() {
+        let breaker = breaker();
+
+        fail(&breaker, Error::Timeout);
+        succeed(&breaker);
+        fail(&breaker, Error::Timeout);
+
+        assert!(!is_rejected(&breaker));
+    }
+
+    #[test]
+    fn ignores_errors_that_are_no_outage() {
+        let breaker = breaker();
+
+        for _ in 0..3 {
+            fail(&breaker, Error::Status { status: StatusCode::BAD_REQUEST, body: String::new() });
+            fail(&breaker, Error::Compiler("oops".into()));
+        }
+
+        assert!(!is_rejected(&breaker));
+    }
+
+    #[test]
+    fn keeps_rejecting_while_open() {
+        let breaker = breaker();
+        fail(&breaker, Error::Timeout);
+        fail(&breaker, Error::Timeout);
+
+        // Rejected requests must not close the circuit again
+        assert!(is_rejected(&breaker));
+        fail(&breaker, Error::Unavailable);
+        assert!(is_rejected(&breaker));
+        assert!(is_rejected(&breaker));
+    }
+
+    #[test]
+    fn half_open_admits_a_single_trial() {
+        let breaker = breaker();
+        fail(&breaker, Error::Timeout);
+        fail(&breaker, Error::Timeout);
+        cool_down(&breaker);
+
+        assert!(!is_rejected(&breaker));
+        assert!(is_rejected(&breaker));
+        fail(&breaker, Error::Unavailable);
+        assert!(is_rejected(&breaker));
+
+        // The trial succeeded
+        succeed(&breaker);
+        assert!(!is_rejected(&breaker));
+        assert!(!is_rejected(&breaker));
+    }
+
+    #[test]
+    fn failed_trial_opens_again() {
+        let breaker = breaker();
+        fail(&breaker, Error::Timeout);
+        fail(&breaker, Error::Timeout);
+        cool_down(&breaker);
+
+        assert!(!is_rejected(&breaker));
+        fail(&breaker, Error::Timeout);
+        assert!(is_rejected(&breaker));
+    }
+
+    #[test]
+    fn disabled_never_opens() {
+        let breaker = CircuitBreaker::disabled();
+
+        for _ in 0..10 {
+            fail(&breaker, Error::Timeout);
+        }
+
+        assert!(!is_rejected(&breaker));
+    }
+
+    #[test]
+    fn backs_off_exponentially() {
+        let retry = RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(300),
+        };
+
+        assert_eq!(retry.backoff(0, &Error::Timeout, true), Some(Duration::from_millis(100)));
+        assert_eq!(retry.backoff(1, &Error::Timeout, true), Some(Duration::from_millis(200)));
+        assert_eq!(retry.backoff(2, &Error::Timeout, true), Some(Duration::from_millis(300)));
+        assert_eq!(retry.backoff(3, &Error::Timeout, true), None);
+    }
+
+    #[test]
+    fn retries_only_what_may_succeed() {
+        let retry = RetryPolicy::default();
+        let rate_limited = Error::RateLimited { retry_after: Some(Duration::from_secs(2)) };
+
+        assert_eq!(retry.backoff(0, &rate_limited, false), Some(Duration::from_secs(2)));
+        assert_eq!(retry.backoff(0, &Error::Timeout, false), None);
+        assert_eq!(retry.backoff(0, &Error::Compiler("oops".into()), true), None);
+        assert_eq!(retry.backoff(0, &Error::Unavailable, true), None);
+    }
+}
@@ -1,27 +1,15 @@
-use crate::Channel;
-use reqwest::{Client, Error};
-use reqwest::r#async as async_reqwest;
+use crate::{Channel, PlaygroundClient, Error};
+use crate::client::{send_json, async_send_json};
 use futures::prelude::*;
 
-pub fn version(client: &Client, channel: Channel) -> Result<Version, Error> {
-    let resp = client
-        .get(&format!("https://play.rust-lang.org/meta/version/{}", channel.as_str()))
-        .send()?
-        .error_for_status()?
-        .json()?;
-
-    Ok(resp)
+pub fn version(client: &PlaygroundClient, channel: Channel) -> Result<Version, Error> {
+    let path = format!("/meta/version/{}", channel.as_str());
+    send_json(client, client.get(&path))
 }
 
-pub fn async_version(channel: Channel) -> impl Future<Item = Version, Error = Error> {
-    let client = async_reqwest::Client::new();
-    let url = format!("https://play.rust-lang.org/meta/version/{}", channel.as_str());
-
-    client
-    .get(&url)
-    .send()
-    .and_then(|resp| resp.error_for_status())
-    .and_then(|mut resp| resp.json())
+pub fn async_version(client: &PlaygroundClient, channel: Channel) -> impl Future<Item = Version, Error = Error> {
+    let path = format!("/meta/version/{}", channel.as_str());
+    async_send_json(client, client.get(&path))
 }
 
 #[derive(Deserialize)]
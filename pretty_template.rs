@@ -0,0 +1,9 @@
+#![allow(unreachable_code)]
+{crate_attrs}
+{items}
+
+fn main() {{
+    println!("{{:#?}}", {{
+        {code}
+    }});
+}}
@@ -1,4 +1,4 @@
-use playbot::{Playbot, Message};
+use playbot::{Playbot, Message, Config};
 use failure::Error;
 use shared_str::ArcStr;
 use rustyline::error::ReadlineError;
@@ -30,6 +30,10 @@ impl Message for CliMessage {
         Ok(())
     }
 
+    fn channel(&self) -> Option<ArcStr> {
+        None
+    }
+
     fn source_nickname(&self) -> ArcStr {
         "".into()
     }
@@ -41,7 +45,7 @@ impl Message for CliMessage {
 
 fn main() {
     let logger = Logger::root(Discard, o!());
-    let playbot = Playbot::new();
+    let playbot = Playbot::new(Config::default()).expect("failed to start playbot");
     let mut rl = rustyline::Editor::<()>::new();
 
     loop {
@@ -0,0 +1,273 @@
+use std::sync::Arc;
+use std::time::Duration;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use failure::{Error, err_msg};
+use playground::{
+    PlaygroundClient, RetryPolicy, CircuitBreaker, Cache,
+    Backend, LocalBackend, Sandbox, Limits, Edition, DEFAULT_BASE_URL,
+};
+use crate::Templates;
+
+/// Per-instance bot settings.
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Config {
+    pub playground: PlaygroundConfig,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct PlaygroundConfig {
+    /// Base URL of the playground deployment to evaluate code on.
+    pub url: String,
+    /// Maximum duration of a single playground request in seconds, `0` disables the timeout.
+    pub timeout_secs: u64,
+    pub connect_timeout_secs: u64,
+    /// How often failed requests are retried, with exponential backoff.
+    pub max_retries: u32,
+    /// Consecutive failures after which the playground is considered down, `0` disables this.
+    pub unavailable_after_failures: u32,
+    /// How long the playground is considered down before trying again.
+    pub unavailable_secs: u64,
+    /// How long identical requests are answered from the cache.
+    pub cache_secs: u64,
+    /// Number of cached responses, `0` disables the cache.
+    pub cache_entries: usize,
+    pub cache_mb: usize,
+    /// Edition used when no edition flag is given.
+    pub edition: Edition,
+    /// Where code gets compiled and run.
+    pub backend: BackendConfig,
+    /// How much of the output is shown in the channel.
+    pub output: OutputConfig,
+    /// Directory with additional templates, `<name>.rs` is selected with `--<name>`.
+    pub templates_dir: Option<PathBuf>,
+    /// Additional templates defined in the config itself.
+    pub templates: Vec<TemplateConfig>,
+    /// Where functions stored with `?def` are saved, they're lost on reconnect if unset.
+    pub codedb: Option<PathBuf>,
+    /// Code collected with `?buf add` is dropped after this long without being used.
+    pub buffer_expiry_secs: u64,
+    /// Paste sites whose links serve the code as plain text, e.g. `paste.rs`.
+    pub raw_paste_hosts: Vec<String>,
+}
+
+impl PlaygroundConfig {
+    pub fn client(&self) -> Result<PlaygroundClient, playground::Error> {
+        PlaygroundClient::builder()
+            .base_url(self.url.as_str())
+            .timeout(optional_secs(self.timeout_secs))
+            .connect_timeout(optional_secs(self.connect_timeout_secs))
+            .retry_policy(RetryPolicy {
+                max_retries: self.max_retries,
+                ..RetryPolicy::default()
+            })
+            .circuit_breaker(CircuitBreaker::new(
+                self.unavailable_after_failures,
+                Duration::from_secs(self.unavailable_secs),
+            ))
+            .cache(Cache::new(
+                Duration::from_secs(self.cache_secs),
+                self.cache_entries,
+                self.cache_mb * 1024 * 1024,
+            ))
+            .build()
+    }
+
+    pub fn backend(&self, client: &PlaygroundClient) -> Result<Arc<Backend>, Error> {
+        Ok(match &self.backend {
+            BackendConfig::Playground => Arc::new(client.clone()),
+            BackendConfig::Local(local) => Arc::new(local.backend()?),
+        })
+    }
+
+    /// Loads and validates the operator's templates.
+    pub fn templates(&self) -> Result<Templates, Error> {
+        Templates::load(self.templates_dir.as_ref().map(|dir| dir.as_path()), &self.templates)
+    }
+}
+
+/// Durations where `0` means "no limit".
+fn optional_secs(secs: u64) -> Option<Duration> {
+    if secs == 0 {
+        None
+    } else {
+        Some(Duration::from_secs(secs))
+    }
+}
+
+impl Default for PlaygroundConfig {
+    fn default() -> Self {
+        Self {
+            url: DEFAULT_BASE_URL.into(),
+            timeout_secs: 30,
+            connect_timeout_secs: 10,
+            max_retries: 2,
+            unavailable_after_failures: 5,
+            unavailable_secs: 60,
+            cache_secs: 600,
+            cache_entries: 512,
+            cache_mb: 16,
+            edition: Edition::default(),
+            backend: BackendConfig::Playground,
+            output: OutputConfig::default(),
+            templates_dir: None,
+            templates: Vec::new(),
+            codedb: None,
+            buffer_expiry_secs: 900,
+            raw_paste_hosts: vec![
+                "paste.rs".into(),
+                "pastebin.com".into(),
+                "bpa.st".into(),
+                "dpaste.com".into(),
+                "termbin.com".into(),
+                "sprunge.us".into(),
+            ],
+        }
+    }
+}
+
+/// A template defined in the config, selected with `--<name>`.
+#[derive(Deserialize, Clone)]
+pub struct TemplateConfig {
+    pub name: String,
+    /// Shown by `?templates`.
+    pub description: Option<String>,
+    /// The template itself, alternatively it's read from `file`.
+    pub source: Option<String>,
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum BackendConfig {
+    /// Run code on the configured playground.
+    Playground,
+    /// Run code on this machine.
+    Local(LocalBackendConfig),
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct LocalBackendConfig {
+    /// Run code inside this docker image.
+    pub docker_image: Option<String>,
+    /// Without a docker image, code runs as a plain subprocess that can do anything the bot can.
+    /// This has to be enabled explicitly.
+    pub unsafe_unsandboxed: bool,
+    pub cpu_secs: u64,
+    pub memory_mb: u64,
+    pub timeout_secs: u64,
+    pub max_output_bytes: usize,
+}
+
+impl LocalBackendConfig {
+    pub fn backend(&self) -> Result<LocalBackend, Error> {
+        let sandbox = match &self.docker_image {
+            Some(image) => Sandbox::Docker { image: image.clone() },
+            None if self.unsafe_unsandboxed => Sandbox::Process,
+            None => return Err(err_msg(
+                "the local backend needs a `docker_image`, or `unsafe_unsandboxed = true` to run code unsandboxed"
+            )),
+        };
+
+        let limits = Limits {
+            cpu_secs: self.cpu_secs,
+            memory_bytes: self.memory_mb * 1024 * 1024,
+            timeout: Duration::from_secs(self.timeout_secs),
+            max_output_bytes: self.max_output_bytes,
+        };
+
+        Ok(LocalBackend::new(sandbox, limits))
+    }
+}
+
+impl Default for LocalBackendConfig {
+    fn default() -> Self {
+        let limits = Limits::default();
+
+        Self {
+            docker_image: None,
+            unsafe_unsandboxed: false,
+            cpu_secs: limits.cpu_secs,
+            memory_mb: limits.memory_bytes / 1024 / 1024,
+            timeout_secs: limits.timeout.as_secs(),
+            max_output_bytes: limits.max_output_bytes,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct OutputConfig {
+    /// Lines of output shown for a successful run.
+    pub max_lines: usize,
+    /// Lines of output shown for a failed build or run.
+    pub max_error_lines: usize,
+    /// Longer lines are cut off.
+    pub max_line_chars: usize,
+    /// Whether to link the full output when it had to be shortened.
+    pub paste: bool,
+    /// Overrides for individual channels, e.g. `channels."#rust-offtopic"`.
+    pub channels: HashMap<String, OutputOverrides>,
+}
+
+impl OutputConfig {
+    /// Limits for replies in the given channel, `None` for private messages.
+    pub fn limits(&self, channel: Option<&str>) -> OutputLimits {
+        let limits = OutputLimits {
+            max_lines: self.max_lines,
+            max_error_lines: self.max_error_lines,
+            max_line_chars: self.max_line_chars,
+            paste: self.paste,
+        };
+
+        let overrides = channel.and_then(|channel| {
+            self.channels.iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(channel))
+                .map(|(_, overrides)| overrides)
+        });
+
+        match overrides {
+            Some(overrides) => OutputLimits {
+                max_lines: overrides.max_lines.unwrap_or(limits.max_lines),
+                max_error_lines: overrides.max_error_lines.unwrap_or(limits.max_error_lines),
+                max_line_chars: overrides.max_line_chars.unwrap_or(limits.max_line_chars),
+                paste: overrides.paste.unwrap_or(limits.paste),
+            },
+            None => limits,
+        }
+    }
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            max_lines: 3,
+            max_error_lines: 1,
+            max_line_chars: 300,
+            paste: true,
+            channels: HashMap::new(),
+        }
+    }
+}
+
+/// Per-channel settings, unset ones fall back to the instance's `OutputConfig`.
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct OutputOverrides {
+    pub max_lines: Option<usize>,
+    pub max_error_lines: Option<usize>,
+    pub max_line_chars: Option<usize>,
+    pub paste: Option<bool>,
+}
+
+/// The output settings that apply to a single reply.
+#[derive(Copy, Clone, Debug)]
+pub struct OutputLimits {
+    pub max_lines: usize,
+    pub max_error_lines: usize,
+    pub max_line_chars: usize,
+    pub paste: bool,
+}
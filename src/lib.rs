@@ -6,10 +6,20 @@ use std::thread;
 use std::sync::Arc;
 use actix::prelude::*;
 use slog::Logger;
+use failure::Error;
 
 mod message;
 pub use self::message::Message;
 
+mod config;
+pub use self::config::{
+    Config, PlaygroundConfig, BackendConfig, LocalBackendConfig,
+    OutputConfig, OutputOverrides, OutputLimits, TemplateConfig,
+};
+
+mod templates;
+pub use self::templates::{Templates, CustomTemplate, Placeholders};
+
 pub mod modules_ng;
 use modules_ng::{PluginManager, event::OnMessage};
 
@@ -18,7 +28,11 @@ pub struct Playbot {
 }
 
 impl Playbot {
-    pub fn new() -> Self {
+    /// Fails if the config is unusable, e.g. because a template doesn't parse.
+    pub fn new(config: Config) -> Result<Self, Error> {
+        // Anything that depends on the config is set up here rather than in the plugin thread,
+        // where an error could only panic
+        let playground = modules_ng::Playground::load(&config.playground)?;
         let (tx, rx) = std::sync::mpsc::channel();
 
         thread::spawn(move || {
@@ -28,7 +42,7 @@ impl Playbot {
                 let mut pm = PluginManager::new(&ctx);
 
                 pm.register_plugin("help", |ctx| modules_ng::Help::new(ctx));
-                pm.register_plugin("playground", |ctx| modules_ng::Playground::new(ctx));
+                pm.register_plugin("playground", move |ctx| playground.start(ctx));
                 pm.register_plugin("crate_info", |ctx| modules_ng::CrateInfo::new(ctx));
                 pm.register_plugin("egg", |ctx| modules_ng::Egg::new(ctx));
                 pm.register_plugin("genword", |ctx| modules_ng::GenWord::new(ctx));
@@ -43,9 +57,9 @@ impl Playbot {
 
         let plugin_manager = rx.recv().unwrap();
 
-        Self {
+        Ok(Self {
             plugin_manager,
-        }
+        })
     }
 
     pub fn handle_message<M: Message + 'static>(&self, message: M, l: &Logger) {
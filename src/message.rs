@@ -15,6 +15,9 @@ pub trait Message: Send + Sync {
 
     fn reply(&self, message: &str) -> Result<(), Error>;
 
+    /// The channel the message was sent to, or `None` for private messages.
+    fn channel(&self) -> Option<ArcStr>;
+
     fn source_nickname(&self) -> ArcStr;
 
     fn current_nickname(&self) -> ArcStr;
@@ -65,6 +68,10 @@ impl Message for InlineMessage {
         self.message.reply(message)
     }
 
+    fn channel(&self) -> Option<ArcStr> {
+        self.message.channel()
+    }
+
     fn source_nickname(&self) -> ArcStr {
         self.message.source_nickname()
     }
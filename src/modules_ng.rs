@@ -6,7 +6,7 @@ use crate::message::Message;
 use regex::Regex;
 
 mod playground;
-pub(crate) use self::playground::Playground;
+pub(crate) use self::playground::{Playground, Flag};
 
 mod help;
 pub(crate) use self::help::Help;
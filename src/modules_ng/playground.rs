@@ -1,23 +1,103 @@
-use ::playground::{self, ExecuteRequest, Channel, Mode, CrateType};
+use ::playground::{
+    self, ExecuteRequest, ExecuteResponse, CompileRequest, CompileTarget, AssemblyFlavor,
+    ClippyRequest, FormatRequest, MiriRequest, MacroExpansionRequest,
+    Channel, Mode, CrateType, Version, PlaygroundClient, Backend,
+};
 use regex::Regex;
-use reqwest::Client;
 use actix::prelude::*;
 use super::*;
-use crate::Message;
-use std::borrow::Cow;
+use crate::{Message, PlaygroundConfig, OutputLimits, Templates, CustomTemplate};
+use std::sync::Arc;
+use std::time::Duration;
 use slog::Logger;
+use futures::future::{self, Future};
+
+mod libtest;
+use self::libtest::TestSummary;
+
+mod panic;
+use self::panic::Panic;
+
+mod excerpt;
+use self::excerpt::Excerpt;
+
+mod source_map;
+use self::source_map::SourceMap;
+
+mod diagnostic;
+use self::diagnostic::Diagnostic;
+
+mod snippet;
+use self::snippet::{Snippet, split_alternatives};
+
+mod codedb;
+use self::codedb::CodeDb;
+
+mod buffers;
+use self::buffers::Buffers;
+
+mod source;
+use self::source::Fetcher;
+
+mod flag;
+pub(crate) use self::flag::Flag;
 
 lazy_static! {
     static ref CRATE_ATTRS: Regex = Regex::new(r"^(\s*#!\[.*?\])*").unwrap();
+    /// Cargo's closing tally, e.g. ``warning: `playground` (bin "playground") generated 2 warnings``.
+    static ref WARNING_TALLY: Regex = Regex::new(r"generated \d+ warnings?\b").unwrap();
 }
 
-pub(crate) struct Playground {}
+/// Stand in for the user's code while rendering a template.
+const CODE_MARKER: &str = "\u{0}code\u{0}";
+const ITEMS_MARKER: &str = "\u{0}items\u{0}";
+
+/// Per channel, to keep the code database from growing without bounds.
+const MAX_DEFINITIONS: usize = 100;
+
+/// Replies that are still waiting on the playground.
+/// They are spawned on the arbiter, so the actor can handle the next message in the meantime.
+type Reply = Box<Future<Item = (), Error = ()>>;
+
+pub(crate) struct Playground {
+    client: PlaygroundClient,
+    backend: Arc<Backend>,
+    config: PlaygroundConfig,
+    templates: Templates,
+    codedb: CodeDb,
+    buffers: Buffers,
+    fetcher: Fetcher,
+}
 
 impl Playground {
-    pub fn new(ctx: PluginContext<Self>) -> Self {
+    /// Sets up the client, templates and code database, which fails on a bad config.
+    pub fn load(config: &PlaygroundConfig) -> Result<Self, failure::Error> {
+        let client = config.client()?;
+        let fetcher = Fetcher::new(client.base_url(), &config.raw_paste_hosts)?;
+
+        Ok(Self {
+            backend: config.backend(&client)?,
+            client,
+            config: config.clone(),
+            templates: config.templates()?,
+            codedb: CodeDb::open(config.codedb.clone())?,
+            buffers: Buffers::new(Duration::from_secs(config.buffer_expiry_secs)),
+            fetcher,
+        })
+    }
+
+    pub fn start(self, ctx: PluginContext<Self>) -> Self {
         ctx.on_message(Priority::NORMAL, ctx.recipient());
         ctx.on_command("eval", ctx.recipient());
-        Self {}
+        ctx.on_command("playcrates", ctx.recipient());
+        ctx.on_command("versions", ctx.recipient());
+        ctx.on_command("templates", ctx.recipient());
+        ctx.on_command("def", ctx.recipient());
+        ctx.on_command("undef", ctx.recipient());
+        ctx.on_command("defs", ctx.recipient());
+        ctx.on_command("buf", ctx.recipient());
+
+        self
     }
 }
 
@@ -33,7 +113,7 @@ impl Handler<OnMessage> for Playground {
             return;
         }
 
-        execute_code(&*event.message, &event.message.body(), &event.l);
+        self.execute_code(&event.message, &event.message.body(), &event.l);
     }
 }
 
@@ -41,11 +121,82 @@ impl Handler<OnCommand> for Playground {
     type Result = ();
 
     fn handle(&mut self, event: OnCommand, ctx: &mut Context<Self>) {
-        if event.command != "eval" {
-            return;
+        match event.command.as_str() {
+            "eval" => self.execute_code(&event.message, &event.arg, &event.l),
+            "playcrates" => Arbiter::spawn(show_playground_crates(&self.client, event.message, event.arg)),
+            "versions" => Arbiter::spawn(print_versions(&self.client, event.message)),
+            "templates" => list_templates(&self.templates, &*event.message),
+            "def" => define(&mut self.codedb, &*event.message, &event.arg),
+            "undef" => undefine(&mut self.codedb, &*event.message, &event.arg),
+            "defs" => list_definitions(&self.codedb, &*event.message),
+            "buf" => self.handle_buffer(event),
+            _ => {},
         }
+    }
+}
+
+impl Playground {
+    /// Handles `?buf add <line>`, `?buf show`, `?buf run [flags]` and `?buf clear`.
+    fn handle_buffer(&mut self, event: OnCommand) {
+        let message = event.message;
+        let user = message.source_nickname();
+        let arg = event.arg.trim_start();
+        let subcommand = arg.split_whitespace().next().unwrap_or("");
+        // Keep the indentation of added lines, apart from the separating space
+        let rest = &arg[subcommand.len()..];
+        let rest = if rest.starts_with(' ') { &rest[1..] } else { rest };
+
+        match subcommand {
+            "add" => {
+                let reply = match self.buffers.add(&user, rest) {
+                    Some(1) => "~~~ 1 line buffered.".to_owned(),
+                    Some(lines) => format!("~~~ {} lines buffered.", lines),
+                    None => "~~~ Your buffer is full, ?buf run or ?buf clear it.".to_owned(),
+                };
+
+                message.reply(&reply);
+            },
+            "show" => {
+                let code = match self.buffers.code(&user) {
+                    Some(code) => code,
+                    None => {
+                        message.reply("~~~ Your buffer is empty.");
+                        return;
+                    },
+                };
 
-        execute_code(&*event.message, &event.arg, &event.l);
+                Arbiter::spawn(paste_then(&self.client, code, Channel::Stable, Mode::Debug, move |result| match result {
+                    Ok(url) => message.reply(&format!("~~~ Buffer: {}", url)),
+                    Err(e) => {
+                        eprintln!("Failed to paste buffer: {:?}", e);
+                        message.reply(&format!("~~~ Failed to paste buffer: {}", e))
+                    },
+                }));
+            },
+            "run" => {
+                let code = match self.buffers.code(&user) {
+                    Some(code) => code,
+                    None => {
+                        message.reply("~~~ Your buffer is empty.");
+                        return;
+                    },
+                };
+
+                // The flags are parsed as usual, followed by the buffered code
+                let body = format!("{}\n{}", rest, code);
+                self.execute_code(&message, &body, &event.l);
+            },
+            "clear" => {
+                if self.buffers.clear(&user) {
+                    message.reply("~~~ Buffer cleared.");
+                } else {
+                    message.reply("~~~ Your buffer is empty.");
+                }
+            },
+            _ => {
+                message.reply("Usage: ?buf add <line> | ?buf show | ?buf run [flags] | ?buf clear");
+            },
+        }
     }
 }
 
@@ -55,234 +206,1037 @@ enum Template {
     Expr,
     Bare,
     ExprAllocStats,
+    ExprDisplay,
+    ExprPretty,
+    ExprType,
+    ExprBench,
+    /// One of the operator's templates.
+    Custom(Arc<CustomTemplate>),
 }
 
-fn execute_code(message: &Message, mut body: &str, l: &Logger) {
-    let mut request = ExecuteRequest::new("");
-    let mut template = Template::Expr;
+/// What to do with the code once it has been wrapped in its template.
+#[derive(PartialEq, Copy, Clone)]
+enum Action {
+    Run,
+    Compile(CompileTarget, AssemblyFlavor),
+    Clippy,
+    Format,
+    Miri,
+    Expand,
+    Test,
+}
 
-    // Parse flags
-    loop {
-        body = body.trim_start();
-        let flag = body.split_whitespace().next().unwrap_or("");
+impl Playground {
+    /// Evaluates `body`, which starts with any flags, and replies to `message`.
+    fn execute_code(&self, message: &Arc<Message>, mut body: &str, l: &Logger) {
+        let mut request = ExecuteRequest::new("");
+        request.set_edition(Some(self.config.edition));
+        let mut template = Template::Expr;
+        let mut action = Action::Run;
+        let mut assembly_flavor = AssemblyFlavor::Att;
 
-        match flag {
-            "--stable" => request.set_channel(Channel::Stable),
-            "--beta" => request.set_channel(Channel::Beta),
-            "--nightly" => request.set_channel(Channel::Nightly),
-            "--version" | "VERSION" => {
-                print_version(request.channel(), &*message);
-                return;
-            },
-            "--bare" | "--mini" => template = Template::Bare,
-            "--allocs" | "--alloc" | "--stats" | "--alloc-stats" => template = Template::ExprAllocStats,
-            "--debug" => request.set_mode(Mode::Debug),
-            "--release" => request.set_mode(Mode::Release),
-            "--2015" => request.set_edition(Some("2015".to_owned())),
-            "--2018" => request.set_edition(Some("2018".to_owned())),
-            "help" | "h" | "-h" | "-help" | "--help" | "--h" => {
-                super::help::display_help(message);
-                return;
-            },
-            "--" => {
+        // Parse flags
+        loop {
+            body = body.trim_start();
+            let flag = body.split_whitespace().next().unwrap_or("");
+
+            if flag == "--" {
                 body = &body[flag.len()..];
                 break;
-            },
-            _ => break,
-        }
+            }
 
-        body = &body[flag.len()..];
-    }
+            match Flag::parse(flag) {
+                Some(Flag::Channel(channel)) => request.set_channel(channel),
+                Some(Flag::Version) => {
+                    let all = body[flag.len()..].split_whitespace().next() == Some("--all");
 
-    let mut body = Cow::Borrowed(body.trim_start());
+                    if all {
+                        Arbiter::spawn(print_versions(&self.client, message.clone()));
+                    } else {
+                        Arbiter::spawn(print_version(&self.client, request.channel(), message.clone()));
+                    }
 
-    if gist::is_gist_or_raw_gist_url(&body) {
-        template = Template::Bare;
-        match gist::fetch_gist(&body) {
-            Ok(file) => body = Cow::Owned(file),
-            Err(e) => {
-                error!(l, "[ERR/gist/{}]: {}", body, e);
-                message.reply("Failed to fetch gist");
-                return;
+                    return;
+                },
+                Some(Flag::Bare) => template = Template::Bare,
+                Some(Flag::AllocStats) => template = Template::ExprAllocStats,
+                Some(Flag::Display) => template = Template::ExprDisplay,
+                Some(Flag::Pretty) => template = Template::ExprPretty,
+                Some(Flag::Type) => template = Template::ExprType,
+                Some(Flag::Bench) => {
+                    template = Template::ExprBench;
+                    request.set_mode(Mode::Release);
+                    request.set_no_cache(true);
+                },
+                Some(Flag::Backtrace) => request.set_backtrace(true),
+                Some(Flag::Debug) => request.set_mode(Mode::Debug),
+                Some(Flag::Release) => request.set_mode(Mode::Release),
+                Some(Flag::Edition(edition)) => request.set_edition(Some(edition)),
+                Some(Flag::Compile(target)) => {
+                    action = Action::Compile(target, assembly_flavor);
+
+                    if target == CompileTarget::Hir || target == CompileTarget::Wasm {
+                        request.set_channel(Channel::Nightly);
+                    }
+                },
+                Some(Flag::Intel) => assembly_flavor = AssemblyFlavor::Intel,
+                Some(Flag::Clippy) => action = Action::Clippy,
+                Some(Flag::Format) => action = Action::Format,
+                Some(Flag::Miri) => {
+                    action = Action::Miri;
+                    request.set_channel(Channel::Nightly);
+                },
+                Some(Flag::Test) => {
+                    action = Action::Test;
+                    template = Template::Bare;
+                    request.set_tests(true);
+                },
+                Some(Flag::Expand) => {
+                    action = Action::Expand;
+                    request.set_channel(Channel::Nightly);
+                },
+                Some(Flag::Help) => {
+                    super::help::display_help(&**message);
+                    return;
+                },
+                None => match flag.get(2..).filter(|_| flag.starts_with("--")).and_then(|name| self.templates.get(name)) {
+                    Some(custom) => template = Template::Custom(custom.clone()),
+                    None => break,
+                },
             }
+
+            body = &body[flag.len()..];
+        }
+
+        // rustfmt gets the code as it was written, otherwise the template would end up in the formatted code
+        if let Action::Format = action {
+            template = Template::Bare;
+        }
+
+        // `--intel` may come before or after `--asm`
+        if let Action::Compile(_, flavor) = &mut action {
+            *flavor = assembly_flavor;
+        }
+
+        let body = body.trim_start();
+        let limits = self.config.output.limits(message.channel().as_ref().map(|channel| &**channel));
+
+        // Linked code is run as it is, once it has been fetched
+        if let Some(fetch) = self.fetcher.fetch(body) {
+            let client = self.client.clone();
+            let backend = self.backend.clone();
+            let message = message.clone();
+            let url = body.to_owned();
+            let l = l.clone();
+
+            Arbiter::spawn(fetch.then(move |result| match result {
+                Ok(code) => {
+                    detect_crate_type(&mut request, &code);
+                    request.set_code(code);
+                    run_action(&client, &*backend, message, request, SourceMap::identity(), action, limits)
+                },
+                Err(e) => {
+                    error!(l, "[ERR/fetch/{}]: {}", url, e);
+                    message.reply(&format!("Failed to fetch code: {}", e));
+                    done()
+                },
+            }));
+
+            return;
         }
+
+        if template == Template::Bare {
+            detect_crate_type(&mut request, body);
+        }
+
+        let scope = scope(&**message);
+        // The functions stored with `?def` that a piece of the code uses,
+        // except for the ones it defines itself
+        let definitions = |code: &str, snippet: &Snippet| {
+            self.codedb.referenced(&scope, code, &snippet.item_names(body)).join("\n")
+        };
+
+        let (code, source_map) = match template {
+            Template::Bare => (body.to_owned(), SourceMap::identity()),
+            Template::Expr => wrap_expr(body, &definitions, |crate_attrs, items| format!(include_str!("../../template.rs"),
+                crate_attrs = crate_attrs,
+                items = items,
+                code = CODE_MARKER,
+            )),
+            Template::ExprAllocStats => wrap_expr(body, &definitions, |crate_attrs, items| format!(include_str!("../../alloc_stats_template.rs"),
+                crate_attrs = crate_attrs,
+                items = items,
+                code = CODE_MARKER,
+            )),
+            Template::ExprDisplay => wrap_expr(body, &definitions, |crate_attrs, items| format!(include_str!("../../display_template.rs"),
+                crate_attrs = crate_attrs,
+                items = items,
+                code = CODE_MARKER,
+            )),
+            Template::ExprPretty => wrap_expr(body, &definitions, |crate_attrs, items| format!(include_str!("../../pretty_template.rs"),
+                crate_attrs = crate_attrs,
+                items = items,
+                code = CODE_MARKER,
+            )),
+            Template::ExprType => wrap_expr(body, &definitions, |crate_attrs, items| format!(include_str!("../../type_template.rs"),
+                crate_attrs = crate_attrs,
+                items = items,
+                code = CODE_MARKER,
+            )),
+            Template::ExprBench => wrap_bench(body, &definitions),
+            Template::Custom(custom) => wrap_custom(body, &custom),
+        };
+
+        request.set_code(code);
+
+        Arbiter::spawn(run_action(&self.client, &*self.backend, message.clone(), request, source_map, action, limits));
     }
+}
 
-    if template == Template::Bare {
-        if let Ok(syn::File { attrs, items, .. }) = syn::parse_str::<syn::File>(&body) {
-            let main_exists = items.iter().any(|item| match item {
-                syn::Item::Fn(fun) => fun.ident == "main",
-                _ => false,
-            });
+/// Bare code is a library unless it has a `main` function or a `#![crate_type]` attribute says otherwise.
+fn detect_crate_type(request: &mut ExecuteRequest, body: &str) {
+    let syn::File { attrs, items, .. } = match syn::parse_str::<syn::File>(body) {
+        Ok(file) => file,
+        Err(_) => return,
+    };
 
-            if !main_exists {
-                request.set_crate_type(CrateType::Lib);
-            }
+    let main_exists = items.iter().any(|item| match item {
+        syn::Item::Fn(fun) => fun.ident == "main",
+        _ => false,
+    });
+
+    if !main_exists {
+        request.set_crate_type(CrateType::Lib);
+    }
 
-            for attr in attrs {
-                match attr.parse_meta().unwrap() {
-                    syn::Meta::NameValue(syn::MetaNameValue { ident, lit: syn::Lit::Str(lit_str), .. }) => {
-                        if ident != "crate_type" { continue; }
+    for attr in attrs {
+        match attr.parse_meta().unwrap() {
+            syn::Meta::NameValue(syn::MetaNameValue { ident, lit: syn::Lit::Str(lit_str), .. }) => {
+                if ident != "crate_type" { continue; }
 
-                        match lit_str.value().as_str() {
-                            "bin" => request.set_crate_type(CrateType::Bin),
-                            "lib" => request.set_crate_type(CrateType::Lib),
-                            _ => (),
-                        }
-                    },
+                match lit_str.value().as_str() {
+                    "bin" => request.set_crate_type(CrateType::Bin),
+                    "lib" => request.set_crate_type(CrateType::Lib),
                     _ => (),
                 }
-            }
-        };
+            },
+            _ => (),
+        }
+    }
+}
+
+fn run_action(client: &PlaygroundClient, backend: &Backend, message: Arc<Message>, request: ExecuteRequest<'static>, source_map: SourceMap, action: Action, limits: OutputLimits) -> Reply {
+    match action {
+        Action::Run => execute(client, backend, message, request, source_map, limits),
+        Action::Compile(target, assembly_flavor) => {
+            let mut compile_request = CompileRequest::from_execute(&request, target);
+            compile_request.set_assembly_flavor(assembly_flavor);
+            compile(client, message, &compile_request, limits)
+        },
+        Action::Clippy => clippy(client, message, request),
+        Action::Format => format_code(client, message, request),
+        Action::Miri => miri(client, message, request, source_map, limits),
+        Action::Expand => expand(client, message, request, limits),
+        Action::Test => test(client, backend, message, request, source_map, limits),
     }
+}
 
-    let code = match template {
-        Template::Bare => body.to_string(),
-        Template::Expr => {
-            let crate_attrs = CRATE_ATTRS.find(&body)
-                .map(|attr| attr.as_str())
-                .unwrap_or("");
+/// Wraps the user's code in one of the expression templates.
+///
+/// `definitions` returns the stored functions needed by the code.
+/// `render` gets the crate attributes found at the start of the code and the items
+/// and renders the template with `CODE_MARKER` in place of the code.
+fn wrap_expr(body: &str, definitions: impl Fn(&str, &Snippet) -> String, render: impl FnOnce(&str, &str) -> String) -> (String, SourceMap) {
+    let crate_attrs = CRATE_ATTRS.find(body)
+        .map(|attr| attr.as_str())
+        .unwrap_or("");
 
-            let body_code = &body[crate_attrs.len()..];
+    let snippet = Snippet::split(body, crate_attrs.len()..body.len());
+    let items = format!("{}\n{}", ITEMS_MARKER, definitions(&body[crate_attrs.len()..], &snippet));
+    let rendered = render(crate_attrs, &items);
 
-            format!(include_str!("../../template.rs"),
-                crate_attrs = crate_attrs,
-                code = body_code,
-            )
+    SourceMap::splice(&rendered, body, &[
+        (ITEMS_MARKER, &snippet.items[..]),
+        (CODE_MARKER, &snippet.code[..]),
+    ])
+}
+
+/// Wraps the user's code in the benchmark template.
+///
+/// Each `;;`-separated alternative gets its own benchmark in a module of its own,
+/// so alternatives can define items with the same names.
+fn wrap_bench(body: &str, definitions: impl Fn(&str, &Snippet) -> String) -> (String, SourceMap) {
+    let crate_attrs = CRATE_ATTRS.find(body)
+        .map(|attr| attr.as_str())
+        .unwrap_or("");
+
+    let alternatives = split_alternatives(body, crate_attrs.len()..body.len());
+    let snippets = alternatives.iter()
+        .map(|alternative| Snippet::split(body, alternative.clone()))
+        .collect::<Vec<_>>();
+
+    let markers = (0..snippets.len())
+        .map(|i| (format!("\u{0}items{}\u{0}", i), format!("\u{0}bench{}\u{0}", i)))
+        .collect::<Vec<_>>();
+
+    let benches = (0..snippets.len())
+        .map(|i| format!("__alternative{}::__run(),", i))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let modules = markers.iter()
+        .zip(alternatives.iter().zip(&snippets))
+        .enumerate()
+        .map(|(i, ((items, code), (alternative, snippet)))| format!(
+            "mod __alternative{} {{\n{}\n{}\npub fn __run() -> super::__bench::Stats {{ super::__bench::run(|| {{\n{}\n}}) }}\n}}",
+            i, items, definitions(&body[alternative.clone()], snippet), code,
+        ))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let rendered = format!(include_str!("../../bench_template.rs"),
+        crate_attrs = crate_attrs,
+        benches = benches,
+        alternatives = modules,
+    );
+
+    let insertions = markers.iter()
+        .zip(&snippets)
+        .flat_map(|((items, code), snippet)| vec![
+            (items.as_str(), &snippet.items[..]),
+            (code.as_str(), &snippet.code[..]),
+        ])
+        .collect::<Vec<_>>();
+
+    SourceMap::splice(&rendered, body, &insertions)
+}
+
+/// Wraps the user's code in one of the operator's templates.
+///
+/// Crate attributes and items are only split off if the template has a place for them.
+fn wrap_custom(body: &str, template: &CustomTemplate) -> (String, SourceMap) {
+    let placeholders = template.placeholders();
+    let crate_attrs = if placeholders.crate_attrs {
+        CRATE_ATTRS.find(body).map(|attr| attr.as_str()).unwrap_or("")
+    } else {
+        ""
+    };
+
+    let rendered = template.render(crate_attrs, ITEMS_MARKER, CODE_MARKER);
+
+    if placeholders.items {
+        let snippet = Snippet::split(body, crate_attrs.len()..body.len());
+
+        return SourceMap::splice(&rendered, body, &[
+            (ITEMS_MARKER, &snippet.items[..]),
+            (CODE_MARKER, &snippet.code[..]),
+        ]);
+    }
+
+    let code = crate_attrs.len()..body.len();
+    SourceMap::splice(&rendered, body, &[(CODE_MARKER, &[code][..])])
+}
+
+fn list_templates(templates: &Templates, message: &Message) {
+    if templates.is_empty() {
+        message.reply("There are no additional templates.");
+        return;
+    }
+
+    let mut templates = templates.iter()
+        .map(|template| match template.description() {
+            Some(description) => format!("--{} ({})", template.name(), description),
+            None => format!("--{}", template.name()),
+        })
+        .collect::<Vec<_>>();
+    templates.sort();
+
+    message.reply(&format!("Templates: {}", templates.join(", ")));
+}
+
+/// Definitions are shared by everyone in a channel, private messages get their own.
+fn scope(message: &Message) -> String {
+    match message.channel() {
+        Some(channel) => channel.to_lowercase(),
+        None => message.source_nickname().to_lowercase(),
+    }
+}
+
+/// Handles `?def <name> <fn item>`.
+fn define(codedb: &mut CodeDb, message: &Message, arg: &str) {
+    let arg = arg.trim();
+    let name = arg.split_whitespace().next().unwrap_or("");
+    let code = arg[name.len()..].trim();
+
+    if name.is_empty() || code.is_empty() {
+        message.reply("Usage: ?def <name> <fn item>, e.g. ?def square fn square(x: i32) -> i32 { x * x }");
+        return;
+    }
+
+    match syn::parse_str::<syn::ItemFn>(code) {
+        Ok(ref fun) if fun.ident == name => {},
+        Ok(fun) => {
+            message.reply(&format!("The function is called '{}', not '{}'.", fun.ident, name));
+            return;
+        },
+        Err(e) => {
+            message.reply(&format!("'{}' is not a function: {}", name, e));
+            return;
         },
-        Template::ExprAllocStats => {
-            let crate_attrs = CRATE_ATTRS.find(&body)
-                .map(|attr| attr.as_str())
-                .unwrap_or("");
+    }
 
-            let body_code = &body[crate_attrs.len()..];
+    let scope = scope(message);
 
-            format!(include_str!("../../alloc_stats_template.rs"),
-                crate_attrs = crate_attrs,
-                code = body_code,
-            )
+    if codedb.len(&scope) >= MAX_DEFINITIONS && !codedb.names(&scope).contains(&name) {
+        message.reply(&format!("There are already {} definitions here, ?undef some first.", MAX_DEFINITIONS));
+        return;
+    }
+
+    let reply = match codedb.define(&scope, name, code) {
+        Ok(true) => format!("Updated '{}'.", name),
+        Ok(false) => format!("Defined '{}'.", name),
+        Err(e) => {
+            eprintln!("Failed to store definition: {:?}", e);
+            format!("Failed to store definition: {}", e)
         },
     };
 
-    request.set_code(code);
-    execute(&*message, &request);
+    message.reply(&reply);
 }
 
-fn print_version<'a>(channel: Channel, message: &Message) {
-    let http = Client::new();
-    let resp = match playground::version(&http, channel) {
-        Err(e) => return eprintln!("Failed to get version: {:?}", e),
-        Ok(resp) => resp,
+/// Handles `?undef <name>`.
+fn undefine(codedb: &mut CodeDb, message: &Message, arg: &str) {
+    let name = arg.trim();
+
+    let reply = match codedb.undefine(&scope(message), name) {
+        Ok(true) => format!("Removed '{}'.", name),
+        Ok(false) => format!("'{}' is not defined.", name),
+        Err(e) => {
+            eprintln!("Failed to remove definition: {:?}", e);
+            format!("Failed to remove definition: {}", e)
+        },
     };
 
-    let version = format!("{version} ({hash:.9} {date})",
+    message.reply(&reply);
+}
+
+fn list_definitions(codedb: &CodeDb, message: &Message) {
+    let names = codedb.names(&scope(message));
+
+    if names.is_empty() {
+        message.reply("Nothing is defined here yet. Try ?def <name> <fn item>");
+        return;
+    }
+
+    message.reply(&format!("Definitions: {}", names.join(", ")));
+}
+
+fn show_playground_crates(client: &PlaygroundClient, message: Arc<Message>, query: String) -> Reply {
+    let max_suggestions = 5;
+
+    Box::new(playground::async_crates(client).then(move |result| -> Result<(), ()> {
+        let crates = match result {
+            Ok(crates) => crates,
+            Err(e) => return {
+                eprintln!("Failed to get playground crates: {:?}", e);
+                message.reply(&format!("Failed to get playground crates: {}", e));
+                Ok(())
+            },
+        };
+
+        let names = query.split_whitespace().take(3).collect::<Vec<_>>();
+
+        if names.is_empty() {
+            message.reply(&format!("{} crates are available in the playground. Try ?playcrates <name>", crates.crates().len()));
+            return Ok(());
+        }
+
+        for name in names {
+            if let Some(krate) = crates.find(name) {
+                message.reply(&format!("{} {} is available in the playground.", krate.name(), krate.version()));
+                continue;
+            }
+
+            let suggestions = crates.search(name)
+                .take(max_suggestions)
+                .map(|krate| format!("{} {}", krate.name(), krate.version()))
+                .collect::<Vec<_>>();
+
+            if suggestions.is_empty() {
+                message.reply(&format!("'{}' is not available in the playground.", name));
+            } else {
+                message.reply(&format!("'{}' is not available in the playground. Similar: {}", name, suggestions.join(", ")));
+            }
+        }
+
+        Ok(())
+    }))
+}
+
+fn print_version(client: &PlaygroundClient, channel: Channel, message: Arc<Message>) -> Reply {
+    Box::new(playground::async_version(client, channel).then(move |result| -> Result<(), ()> {
+        let resp = match result {
+            Ok(resp) => resp,
+            Err(e) => return {
+                eprintln!("Failed to get version: {:?}", e);
+                message.reply(&format!("Failed to get version: {}", e));
+                Ok(())
+            },
+        };
+
+        message.reply(&format_version(&resp));
+        Ok(())
+    }))
+}
+
+/// Replies with the versions of all channels in one line,
+/// e.g. `stable 1.x (hash date) | beta … | nightly …`.
+fn print_versions(client: &PlaygroundClient, message: Arc<Message>) -> Reply {
+    let channels = vec![Channel::Stable, Channel::Beta, Channel::Nightly];
+    let versions = channels.into_iter().map(|channel| {
+        playground::async_version(client, channel).then(move |result| -> Result<String, ()> {
+            let version = match result {
+                Ok(resp) => format_version(&resp),
+                Err(e) => {
+                    eprintln!("Failed to get {} version: {:?}", channel.as_str(), e);
+                    format!("unknown ({})", e)
+                },
+            };
+
+            Ok(format!("{} {}", channel.as_str(), version))
+        })
+    });
+
+    Box::new(future::join_all(versions).map(move |versions| {
+        message.reply(&versions.join(" | "));
+    }))
+}
+
+fn format_version(resp: &Version) -> String {
+    format!("{version} ({hash:.9} {date})",
         version = resp.version,
         hash = resp.hash,
         date = resp.date,
-    );
+    )
+}
+
+pub fn execute(client: &PlaygroundClient, backend: &Backend, message: Arc<Message>, request: ExecuteRequest<'static>, source_map: SourceMap, limits: OutputLimits) -> Reply {
+    let client = client.clone();
+
+    Box::new(backend.async_execute(&request).then(move |result| {
+        let resp = match result {
+            Ok(resp) => resp,
+            Err(e) => return {
+                eprintln!("Failed to execute code: {:?}", e);
+                message.reply(&format!("Failed to execute code: {}", e));
+                done()
+            },
+        };
 
-    message.reply(&version);
+        reply_output(&client, &message, &request, resp, &source_map, limits)
+    }))
 }
 
-pub fn execute(message: &Message, request: &ExecuteRequest) {
-    let http = Client::new();
-    let resp = match playground::execute(&http, &request) {
-        Ok(resp) => resp,
-        Err(e) => return {
-            eprintln!("Failed to execute code: {:?}", e);
-        },
-    };
+pub fn test(client: &PlaygroundClient, backend: &Backend, message: Arc<Message>, request: ExecuteRequest<'static>, source_map: SourceMap, limits: OutputLimits) -> Reply {
+    let client = client.clone();
+
+    Box::new(backend.async_execute(&request).then(move |result| {
+        let resp = match result {
+            Ok(resp) => resp,
+            Err(e) => return {
+                eprintln!("Failed to run tests: {:?}", e);
+                message.reply(&format!("Failed to run tests: {}", e));
+                done()
+            },
+        };
+
+        let summary = match TestSummary::parse(&resp.stdout) {
+            Some(summary) => summary,
+            // The tests did not run, most likely due to a compile error
+            None => return reply_output(&client, &message, &request, resp, &source_map, limits),
+        };
+
+        if summary.total() == 0 {
+            message.reply("~~~ No tests found. Mark test functions with #[test].");
+            return done();
+        }
+
+        // The summary stands in for libtest's progress lines, anything else counts as output
+        let output = resp.stdout.lines().filter(|line| !libtest::is_progress(line));
+        let excerpt = Excerpt::new(output, limits.max_lines, limits.max_line_chars);
+
+        if !limits.paste || (summary.failures.is_empty() && excerpt.is_complete()) {
+            message.reply(&summary.to_string());
+            return done();
+        }
+
+        let code = format!(include_str!("../../paste_template.rs"),
+            code = request.code(),
+            stdout = resp.stdout,
+            stderr = resp.stderr,
+        );
+
+        paste_then(&client, code, request.channel(), request.mode(), move |result| match result {
+            Ok(url) => message.reply(&format!("{} ~~~ Full output: {}", summary, url)),
+            Err(e) => {
+                eprintln!("Failed to paste test output: {:?}", e);
+                message.reply(&summary.to_string())
+            },
+        })
+    }))
+}
+
+/// Replies with the first lines of the program's output (or the first error)
+/// and pastes the rest.
+fn reply_output(client: &PlaygroundClient, message: &Arc<Message>, request: &ExecuteRequest, resp: ExecuteResponse, source_map: &SourceMap, limits: OutputLimits) -> Reply {
+    // The paste shows the rendered program, so only what is shown in the channel is rewritten
+    let stderr = source_map.rewrite(&resp.stderr);
+
+    if !resp.success {
+        if let Some(panic) = Panic::parse(&stderr) {
+            return reply_panic(client, message, request, &resp, panic);
+        }
 
-    let output = if resp.success { &resp.stdout } else { &resp.stderr };
-    let take_count = if resp.success { 3 } else { 1 };
+        let errors = Diagnostic::parse_all(&resp.stderr)
+            .into_iter()
+            .filter(Diagnostic::is_error)
+            .collect::<Vec<_>>();
+
+        if !errors.is_empty() {
+            return reply_errors(client, message, request, &resp, &errors, source_map, limits);
+        }
+    }
+
+    let output = if resp.success { &resp.stdout } else { &stderr };
+    let max_lines = if resp.success { limits.max_lines } else { limits.max_error_lines };
     let lines = output
         .lines()
-        .filter(|line| {
-            if resp.success {
-                return true;
-            }
-
-            !line.trim().starts_with("Compiling")
-            && !line.trim().starts_with("Finished")
-            && !line.trim().starts_with("Running")
-        });
-    let lines_count = lines.clone().count();
+        .filter(|line| resp.success || !is_build_status(line));
+    let excerpt = Excerpt::new(lines, max_lines, limits.max_line_chars);
 
-    for line in lines.take(take_count) {
+    for line in &excerpt.lines {
         message.reply(line);
     }
 
-    if lines_count == 0 && resp.success {
+    if excerpt.lines.is_empty() && excerpt.more_lines == 0 && resp.success {
         message.reply("~~~ Code compiled successfully without output.");
     }
 
-    if lines_count > take_count {
+    if excerpt.is_complete() {
+        return done();
+    }
+
+    if !limits.paste {
+        if let Some(marker) = excerpt.marker() {
+            message.reply(&marker);
+        }
+
+        return done();
+    }
+
+    let code = format!(include_str!("../../paste_template.rs"),
+        code = request.code(),
+        stdout = resp.stdout,
+        stderr = resp.stderr,
+    );
+    let message = message.clone();
+
+    paste_then(client, code, request.channel(), request.mode(), move |result| match result {
+        Ok(url) => message.reply(&excerpt.annotate(&format!("~~~ Full output: {}", url))),
+        Err(e) => {
+            eprintln!("Failed to paste code: {:?}", e);
+            message.reply(&excerpt.annotate(&format!("~~~ Failed to paste full output: {}", e)))
+        },
+    })
+}
+
+/// Replies with a summary of the first compiler error and links the full output if there are more.
+fn reply_errors(client: &PlaygroundClient, message: &Arc<Message>, request: &ExecuteRequest, resp: &ExecuteResponse, errors: &[Diagnostic], source_map: &SourceMap, limits: OutputLimits) -> Reply {
+    message.reply(&errors[0].summary(source_map));
+
+    let more_errors = match errors.len() - 1 {
+        0 => return done(),
+        1 => "… 1 more error".to_owned(),
+        n => format!("… {} more errors", n),
+    };
+
+    if !limits.paste {
+        message.reply(&more_errors);
+        return done();
+    }
+
+    let code = format!(include_str!("../../paste_template.rs"),
+        code = request.code(),
+        stdout = resp.stdout,
+        stderr = resp.stderr,
+    );
+    let message = message.clone();
+
+    paste_then(client, code, request.channel(), request.mode(), move |result| match result {
+        Ok(url) => message.reply(&format!("{} ~~~ Full output: {}", more_errors, url)),
+        Err(e) => {
+            eprintln!("Failed to paste code: {:?}", e);
+            message.reply(&more_errors)
+        },
+    })
+}
+
+pub fn compile(client: &PlaygroundClient, message: Arc<Message>, request: &CompileRequest, limits: OutputLimits) -> Reply {
+    let client = client.clone();
+    let target = request.target();
+    let channel = request.channel();
+    let mode = request.mode();
+
+    Box::new(playground::async_compile(&client, request).then(move |result| {
+        let resp = match result {
+            Ok(resp) => resp,
+            Err(e) => return {
+                eprintln!("Failed to compile code: {:?}", e);
+                message.reply(&format!("Failed to compile code: {}", e));
+                done()
+            },
+        };
+
+        if !resp.success {
+            let error = resp.stderr
+                .lines()
+                .find(|line| !is_build_status(line))
+                .unwrap_or("~~~ Compilation failed without an error message.");
+            message.reply(error);
+            return done();
+        }
+
+        let label = format!("{} listing", target.as_str());
+        reply_listing(&client, &message, &label, resp.code, channel, mode, limits)
+    }))
+}
+
+pub fn clippy(client: &PlaygroundClient, message: Arc<Message>, request: ExecuteRequest<'static>) -> Reply {
+    let client = client.clone();
+    let mut clippy_request = ClippyRequest::new(request.code());
+    clippy_request.set_edition(request.edition());
+    clippy_request.set_crate_type(request.crate_type());
+
+    Box::new(playground::async_clippy(&client, &clippy_request).then(move |result| {
+        let resp = match result {
+            Ok(resp) => resp,
+            Err(e) => return {
+                eprintln!("Failed to run clippy: {:?}", e);
+                message.reply(&format!("Failed to run clippy: {}", e));
+                done()
+            },
+        };
+
+        let diagnostics = resp.stderr
+            .lines()
+            .filter(|line| is_diagnostic(line))
+            .collect::<Vec<_>>();
+        let warnings = diagnostics.iter().filter(|line| line.starts_with("warning")).count();
+        let errors = diagnostics.len() - warnings;
+
+        if diagnostics.is_empty() {
+            if resp.success {
+                message.reply("~~~ Clippy found no issues.");
+            } else {
+                let error = resp.stderr
+                    .lines()
+                    .find(|line| !is_build_status(line))
+                    .unwrap_or("~~~ Clippy failed without an error message.");
+                message.reply(error);
+            }
+            return done();
+        }
+
+        let take_count = 2;
+
+        for diagnostic in diagnostics.iter().take(take_count) {
+            message.reply(diagnostic);
+        }
+
+        let summary = format!("~~~ {} warning{}, {} error{}",
+            warnings, if warnings == 1 { "" } else { "s" },
+            errors, if errors == 1 { "" } else { "s" },
+        );
+
+        if diagnostics.len() <= take_count {
+            message.reply(&summary);
+            return done();
+        }
+
         let code = format!(include_str!("../../paste_template.rs"),
             code = request.code(),
             stdout = resp.stdout,
             stderr = resp.stderr,
         );
 
-        let url = match playground::paste(&http, code, request.channel(), request.mode()) {
-            Ok(url) => url,
+        paste_then(&client, code, request.channel(), request.mode(), move |result| match result {
+            Ok(url) => message.reply(&format!("{}: {}", summary, url)),
+            Err(e) => {
+                eprintln!("Failed to paste clippy output: {:?}", e);
+                message.reply(&summary)
+            },
+        })
+    }))
+}
+
+pub fn format_code(client: &PlaygroundClient, message: Arc<Message>, request: ExecuteRequest<'static>) -> Reply {
+    let client = client.clone();
+    let mut format_request = FormatRequest::new(request.code());
+    format_request.set_edition(request.edition());
+
+    Box::new(playground::async_format(&client, &format_request).then(move |result| {
+        let resp = match result {
+            Ok(resp) => resp,
             Err(e) => return {
-                eprintln!("Failed to paste code: {:?}", e);
+                eprintln!("Failed to format code: {:?}", e);
+                message.reply(&format!("Failed to format code: {}", e));
+                done()
             },
         };
 
-        message.reply(&format!("~~~ Full output: {}", url));
-    }
+        if !resp.success {
+            let error = resp.stderr
+                .lines()
+                .find(|line| !line.trim().is_empty())
+                .unwrap_or("~~~ Formatting failed without an error message.");
+            message.reply(error);
+            return done();
+        }
+
+        if resp.code.trim() == request.code().trim() {
+            message.reply("~~~ Code is already formatted.");
+            return done();
+        }
+
+        paste_then(&client, resp.code, request.channel(), request.mode(), move |result| match result {
+            Ok(url) => message.reply(&format!("~~~ Formatted code: {}", url)),
+            Err(e) => {
+                eprintln!("Failed to paste formatted code: {:?}", e);
+                message.reply(&format!("~~~ Failed to paste formatted code: {}", e))
+            },
+        })
+    }))
 }
 
-mod gist {
-    use regex::Regex;
-    use std::error::Error;
-    use std::collections::HashMap;
+pub fn miri(client: &PlaygroundClient, message: Arc<Message>, request: ExecuteRequest<'static>, source_map: SourceMap, limits: OutputLimits) -> Reply {
+    let client = client.clone();
+    let mut miri_request = MiriRequest::new(request.code());
+    miri_request.set_edition(request.edition());
 
-    lazy_static! {
-        static ref GIST_URL_RE: Regex = Regex::new(
-            "^(https?://)?gist.github.com/([^/ ]+/)?(?P<id>[0-9a-f]+)/?$"
-        ).unwrap();
+    Box::new(playground::async_miri(&client, &miri_request).then(move |result| {
+        let resp = match result {
+            Ok(resp) => resp,
+            Err(e) => return {
+                eprintln!("Failed to run miri: {:?}", e);
+                message.reply(&format!("Failed to run miri: {}", e));
+                done()
+            },
+        };
+
+        let resp = ExecuteResponse {
+            success: resp.success,
+            stdout: resp.stdout,
+            stderr: resp.stderr,
+        };
+
+        reply_output(&client, &message, &request, resp, &source_map, limits)
+    }))
+}
 
-        static ref RAW_GIST_URL_RE: Regex = Regex::new(
-            "^(https?://)?gist.githubusercontent.com/[^/ ]+/[0-9a-f]+/raw(/.*)?"
-        ).unwrap();
+pub fn expand(client: &PlaygroundClient, message: Arc<Message>, request: ExecuteRequest<'static>, limits: OutputLimits) -> Reply {
+    let client = client.clone();
+    let mut expansion_request = MacroExpansionRequest::new(request.code());
+    expansion_request.set_edition(request.edition());
+
+    Box::new(playground::async_macro_expansion(&client, &expansion_request).then(move |result| {
+        let resp = match result {
+            Ok(resp) => resp,
+            Err(e) => return {
+                eprintln!("Failed to expand macros: {:?}", e);
+                message.reply(&format!("Failed to expand macros: {}", e));
+                done()
+            },
+        };
+
+        if !resp.success {
+            let error = resp.stderr
+                .lines()
+                .find(|line| !is_build_status(line))
+                .unwrap_or("~~~ Macro expansion failed without an error message.");
+            message.reply(error);
+            return done();
+        }
+
+        // The injected prelude is the same for every program and would
+        // take up all of the lines shown in the channel.
+        let expanded = resp.stdout
+            .lines()
+            .skip_while(|line| {
+                let line = line.trim();
+
+                line.is_empty()
+                || line == "#![feature(prelude_import)]"
+                || line == "#![allow(unreachable_code)]"
+                || line == "#[prelude_import]"
+                || line == "#[macro_use]"
+                || (line.starts_with("use std::prelude::") && line.ends_with("::*;"))
+                || line == "extern crate std;"
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        reply_listing(&client, &message, "expanded code", expanded, request.channel(), request.mode(), limits)
+    }))
+}
+
+/// Replies with a one line summary of the panic and pastes the full backtrace.
+fn reply_panic(client: &PlaygroundClient, message: &Arc<Message>, request: &ExecuteRequest, resp: &ExecuteResponse, panic: Panic) -> Reply {
+    if !panic.has_backtrace() {
+        message.reply(&panic.to_string());
+        return done();
     }
 
-    pub fn is_gist_or_raw_gist_url(url: &str) -> bool {
-        RAW_GIST_URL_RE.is_match(url) || GIST_URL_RE.is_match(url)
+    let code = format!(include_str!("../../paste_template.rs"),
+        code = request.code(),
+        stdout = resp.stdout,
+        stderr = resp.stderr,
+    );
+    let message = message.clone();
+
+    paste_then(client, code, request.channel(), request.mode(), move |result| match result {
+        Ok(url) => message.reply(&format!("{} ~~~ Full backtrace: {}", panic, url)),
+        Err(e) => {
+            eprintln!("Failed to paste backtrace: {:?}", e);
+            message.reply(&panic.to_string())
+        },
+    })
+}
+
+/// Replies with the first lines of a generated listing and pastes the full listing.
+fn reply_listing(client: &PlaygroundClient, message: &Arc<Message>, label: &str, listing: String, channel: Channel, mode: Mode, limits: OutputLimits) -> Reply {
+    let lines = listing
+        .lines()
+        .filter(|line| !line.trim().is_empty());
+    let excerpt = Excerpt::new(lines, limits.max_lines, limits.max_line_chars);
+
+    for line in &excerpt.lines {
+        message.reply(line);
     }
 
-    pub fn fetch_gist(url: &str) -> Result<String, Box<Error>> {
-        let url = url.trim();
+    if excerpt.lines.is_empty() && excerpt.more_lines == 0 {
+        message.reply(&format!("~~~ No {} was generated.", label));
+    }
+
+    if excerpt.is_complete() {
+        return done();
+    }
 
-        if RAW_GIST_URL_RE.is_match(url) {
-            let mut file = reqwest::get(url)?;
-            let file = file.text()?;
-            return Ok(file);
+    if !limits.paste {
+        if let Some(marker) = excerpt.marker() {
+            message.reply(&marker);
         }
 
-        let captures = GIST_URL_RE.captures(url)
-            .ok_or("Not a gist url")?;
-        let id = &captures["id"];
-        let url = format!("https://api.github.com/gists/{}", id);
-        let gist = reqwest::get(&url)?.json::<Gist>()?;
-        let file = gist.files.into_iter()
-            .map(|(_, file)| file)
-            .find(|file| file.filename.ends_with(".rs"))
-            .ok_or("No .rs file found in the gist")?;
-
-        Ok(file.content)
+        return done();
+    }
+
+    let label = label.to_owned();
+    let message = message.clone();
+
+    paste_then(client, listing, channel, mode, move |result| match result {
+        Ok(url) => message.reply(&excerpt.annotate(&format!("~~~ Full {}: {}", label, url))),
+        Err(e) => {
+            eprintln!("Failed to paste {}: {:?}", label, e);
+            message.reply(&excerpt.annotate(&format!("~~~ Failed to paste full {}: {}", label, e)))
+        },
+    })
+}
+
+/// Pastes `text` and hands the link (or the reason there is none) to `f`.
+fn paste_then<F, R>(client: &PlaygroundClient, text: String, channel: Channel, mode: Mode, f: F) -> Reply
+where
+    F: FnOnce(Result<String, playground::Error>) -> R + 'static,
+{
+    Box::new(playground::async_paste(client, text, channel, mode).then(move |result| -> Result<(), ()> {
+        f(result);
+        Ok(())
+    }))
+}
+
+fn done() -> Reply {
+    Box::new(future::ok(()))
+}
+
+/// Whether the line starts a compiler or lint diagnostic,
+/// e.g. `warning: unused variable: `x``.
+fn is_diagnostic(line: &str) -> bool {
+    (line.starts_with("warning") || line.starts_with("error"))
+    && !line.starts_with("error: aborting")
+    && !line.starts_with("error: could not compile")
+    && !line.starts_with("error: Could not compile")
+    && !line.ends_with("emitted")
+    && !WARNING_TALLY.is_match(line)
+}
+
+/// Whether the line is one of cargo's progress messages rather than compiler output.
+fn is_build_status(line: &str) -> bool {
+    let line = line.trim();
+
+    line.starts_with("Compiling")
+    || line.starts_with("Checking")
+    || line.starts_with("Finished")
+    || line.starts_with("Running")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Real stderr of `cargo clippy` with one lint of each level.
+    const CLIPPY_STDERR: &str = r#"    Checking playground v0.0.1 (/playground)
+error: equal expressions as operands to `==`
+ --> src/main.rs:3:8
+  |
+3 |     if x == x {
+  |        ^^^^^^
+  |
+  = help: for further information visit https://rust-lang.github.io/rust-clippy/master/index.html#eq_op
+  = note: `#[deny(clippy::eq_op)]` on by default
+
+warning: length comparison to zero
+ --> src/main.rs:7:13
+  |
+7 |     let _ = v.len() == 0;
+  |             ^^^^^^^^^^^^ help: using `is_empty` is clearer and more explicit: `v.is_empty()`
+  |
+  = help: for further information visit https://rust-lang.github.io/rust-clippy/master/index.html#len_zero
+  = note: `#[warn(clippy::len_zero)]` on by default
+
+warning: `playground` (bin "playground") generated 1 warning
+error: could not compile `playground` (bin "playground") due to 1 previous error; 1 warning emitted
+"#;
+
+    #[test]
+    fn finds_lints_in_clippy_output() {
+        let diagnostics = CLIPPY_STDERR.lines().filter(|line| is_diagnostic(line)).collect::<Vec<_>>();
+
+        assert_eq!(diagnostics, vec![
+            "error: equal expressions as operands to `==`",
+            "warning: length comparison to zero",
+        ]);
     }
 
-    #[derive(Deserialize)]
-    struct Gist {
-        files: HashMap<String, File>,
+    #[test]
+    fn skips_cargo_tallies() {
+        assert!(!is_diagnostic(r#"warning: `playground` (bin "playground") generated 2 warnings (run `cargo clippy --fix --bin "playground"` to apply 2 suggestions)"#));
+        assert!(!is_diagnostic(r#"warning: `playground` (lib) generated 1 warning"#));
+        assert!(!is_diagnostic("error: aborting due to 2 previous errors; 1 warning emitted"));
+        assert!(is_diagnostic("warning: unused variable: `generated`"));
     }
 
-    #[derive(Deserialize)]
-    struct File {
-        filename: String,
-        content: String,
+    #[test]
+    fn skips_build_status() {
+        assert!(is_build_status("    Checking playground v0.0.1 (/playground)"));
+        assert!(is_build_status("   Compiling playground v0.0.1 (/playground)"));
+        assert!(!is_build_status("error: equal expressions as operands to `==`"));
     }
 }
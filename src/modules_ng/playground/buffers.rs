@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Limits per buffer, so nobody can make the bot hold on to arbitrary amounts of text.
+const MAX_LINES: usize = 200;
+const MAX_BYTES: usize = 32 * 1024;
+
+/// Code collected line by line with `?buf add`, one buffer per user.
+pub struct Buffers {
+    /// Buffers that weren't touched for this long are dropped.
+    expiry: Duration,
+    buffers: HashMap<String, Buffer>,
+}
+
+struct Buffer {
+    lines: Vec<String>,
+    bytes: usize,
+    last_used: Instant,
+}
+
+impl Buffers {
+    pub fn new(expiry: Duration) -> Self {
+        Self {
+            expiry,
+            buffers: HashMap::new(),
+        }
+    }
+
+    /// Appends a line to the user's buffer and returns how many lines it has,
+    /// or `None` if the buffer is full.
+    pub fn add(&mut self, user: &str, line: &str) -> Option<usize> {
+        self.expire();
+
+        let buffer = self.buffers.entry(user.to_lowercase()).or_insert_with(|| Buffer {
+            lines: Vec::new(),
+            bytes: 0,
+            last_used: Instant::now(),
+        });
+
+        buffer.last_used = Instant::now();
+
+        if buffer.lines.len() >= MAX_LINES || buffer.bytes + line.len() > MAX_BYTES {
+            return None;
+        }
+
+        buffer.lines.push(line.to_owned());
+        buffer.bytes += line.len();
+
+        Some(buffer.lines.len())
+    }
+
+    /// The user's buffered code, or `None` if there is none.
+    pub fn code(&mut self, user: &str) -> Option<String> {
+        self.expire();
+
+        let buffer = self.buffers.get_mut(&user.to_lowercase())?;
+        buffer.last_used = Instant::now();
+
+        Some(buffer.lines.join("\n"))
+    }
+
+    /// Drops the user's buffer, returns whether there was one.
+    pub fn clear(&mut self, user: &str) -> bool {
+        self.expire();
+        self.buffers.remove(&user.to_lowercase()).is_some()
+    }
+
+    fn expire(&mut self) {
+        let expiry = self.expiry;
+        self.buffers.retain(|_, buffer| buffer.last_used.elapsed() < expiry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffers() -> Buffers {
+        Buffers::new(Duration::from_secs(60))
+    }
+
+    #[test]
+    fn collects_lines_per_user() {
+        let mut buffers = buffers();
+
+        assert_eq!(buffers.add("alice", "fn main() {"), Some(1));
+        assert_eq!(buffers.add("Alice", "    println!(\"hi\");"), Some(2));
+        assert_eq!(buffers.add("bob", "1 + 1"), Some(1));
+        assert_eq!(buffers.add("ALICE", "}"), Some(3));
+
+        assert_eq!(buffers.code("alice"), Some("fn main() {\n    println!(\"hi\");\n}".to_owned()));
+        assert_eq!(buffers.code("bob"), Some("1 + 1".to_owned()));
+        assert_eq!(buffers.code("carol"), None);
+    }
+
+    #[test]
+    fn clears_buffers() {
+        let mut buffers = buffers();
+        buffers.add("alice", "1");
+
+        assert!(buffers.clear("Alice"));
+        assert!(!buffers.clear("alice"));
+        assert_eq!(buffers.code("alice"), None);
+    }
+
+    #[test]
+    fn limits_lines() {
+        let mut buffers = buffers();
+
+        for _ in 0..MAX_LINES {
+            assert!(buffers.add("alice", "x").is_some());
+        }
+
+        assert_eq!(buffers.add("alice", "x"), None);
+        assert_eq!(buffers.code("alice").map(|code| code.lines().count()), Some(MAX_LINES));
+    }
+
+    #[test]
+    fn limits_bytes() {
+        let mut buffers = buffers();
+        let line = "x".repeat(MAX_BYTES / 2);
+
+        assert_eq!(buffers.add("alice", &line), Some(1));
+        assert_eq!(buffers.add("alice", &line), Some(2));
+        assert_eq!(buffers.add("alice", "x"), None);
+    }
+
+    #[test]
+    fn expires_unused_buffers() {
+        let mut buffers = Buffers::new(Duration::from_secs(0));
+        buffers.add("alice", "1");
+
+        assert_eq!(buffers.code("alice"), None);
+    }
+}
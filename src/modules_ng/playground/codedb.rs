@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use failure::Error;
+use regex::Regex;
+
+lazy_static! {
+    static ref IDENT: Regex = Regex::new(r"\b[A-Za-z_][A-Za-z0-9_]*\b").unwrap();
+}
+
+/// Functions stored with `?def`, separately for each channel.
+pub struct CodeDb {
+    /// Where the definitions are saved, they only live in memory if `None`.
+    path: Option<PathBuf>,
+    scopes: HashMap<String, BTreeMap<String, String>>,
+}
+
+impl CodeDb {
+    /// Loads the definitions from `path`, starting out empty if it doesn't exist yet.
+    pub fn open(path: Option<PathBuf>) -> Result<Self, Error> {
+        let scopes = match &path {
+            Some(path) => match fs::read_to_string(path) {
+                Ok(json) => serde_json::from_str(&json)?,
+                Err(ref e) if e.kind() == ErrorKind::NotFound => HashMap::new(),
+                Err(e) => return Err(e.into()),
+            },
+            None => HashMap::new(),
+        };
+
+        Ok(Self {
+            path,
+            scopes,
+        })
+    }
+
+    /// Stores `code` as `name`, returns whether an older definition got replaced.
+    pub fn define(&mut self, scope: &str, name: &str, code: &str) -> Result<bool, Error> {
+        let previous = self.scopes.entry(scope.to_owned())
+            .or_insert_with(BTreeMap::new)
+            .insert(name.to_owned(), code.to_owned());
+        let replaced = previous.is_some();
+
+        // Only keep what is saved, so the definitions don't vanish on the next start
+        if let Err(e) = self.save() {
+            self.restore(scope, name, previous);
+            return Err(e);
+        }
+
+        Ok(replaced)
+    }
+
+    /// Removes `name`, returns whether it was defined.
+    pub fn undefine(&mut self, scope: &str, name: &str) -> Result<bool, Error> {
+        let previous = match self.scopes.get_mut(scope).and_then(|definitions| definitions.remove(name)) {
+            Some(previous) => previous,
+            None => return Ok(false),
+        };
+
+        if let Err(e) = self.save() {
+            self.restore(scope, name, Some(previous));
+            return Err(e);
+        }
+
+        Ok(true)
+    }
+
+    /// Puts back the definition of `name` as it was before a failed change.
+    fn restore(&mut self, scope: &str, name: &str, previous: Option<String>) {
+        let definitions = self.scopes.entry(scope.to_owned()).or_insert_with(BTreeMap::new);
+
+        match previous {
+            Some(code) => definitions.insert(name.to_owned(), code),
+            None => definitions.remove(name),
+        };
+    }
+
+    pub fn names(&self, scope: &str) -> Vec<&str> {
+        self.scopes.get(scope)
+            .map(|definitions| definitions.keys().map(|name| name.as_str()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn len(&self, scope: &str) -> usize {
+        self.scopes.get(scope).map_or(0, |definitions| definitions.len())
+    }
+
+    /// The definitions `code` refers to by name, including the ones those refer to in turn.
+    ///
+    /// Names in `shadowed` are defined by the code itself, so their definitions are left out.
+    pub fn referenced(&self, scope: &str, code: &str, shadowed: &[String]) -> Vec<&str> {
+        let definitions = match self.scopes.get(scope) {
+            Some(definitions) => definitions,
+            None => return Vec::new(),
+        };
+
+        let mut referenced = BTreeSet::new();
+        let mut pending = vec![code];
+        let mut seen = shadowed.iter().cloned().collect::<HashSet<_>>();
+
+        while let Some(code) = pending.pop() {
+            for ident in IDENT.find_iter(code) {
+                let name = ident.as_str();
+
+                if !seen.insert(name.to_owned()) {
+                    continue;
+                }
+
+                if let Some(definition) = definitions.get(name) {
+                    referenced.insert(name.to_owned());
+                    pending.push(definition);
+                }
+            }
+        }
+
+        referenced.iter()
+            .map(|name| definitions[name].as_str())
+            .collect()
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        // Write to a temporary file first, so a crash can't leave a truncated database behind
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(&self.scopes)?)?;
+        fs::rename(&tmp_path, path)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codedb() -> CodeDb {
+        let mut codedb = CodeDb::open(None).unwrap();
+        codedb.define("#rust", "square", "fn square(x: u32) -> u32 { x * x }").unwrap();
+        codedb.define("#rust", "cube", "fn cube(x: u32) -> u32 { square(x) * x }").unwrap();
+        codedb.define("#rust", "unused", "fn unused() {}").unwrap();
+        codedb
+    }
+
+    #[test]
+    fn references_by_name() {
+        let codedb = codedb();
+
+        assert_eq!(codedb.referenced("#rust", "square(2)", &[]), vec!["fn square(x: u32) -> u32 { x * x }"]);
+        assert!(codedb.referenced("#rust", "squares(2) + my_square", &[]).is_empty());
+    }
+
+    #[test]
+    fn references_transitively() {
+        let codedb = codedb();
+
+        assert_eq!(codedb.referenced("#rust", "cube(2)", &[]), vec![
+            "fn cube(x: u32) -> u32 { square(x) * x }",
+            "fn square(x: u32) -> u32 { x * x }",
+        ]);
+    }
+
+    #[test]
+    fn leaves_out_shadowed_definitions() {
+        let codedb = codedb();
+
+        assert_eq!(codedb.referenced("#rust", "fn square(x: u32) -> u32 { 0 } cube(2)", &["square".to_owned()]), vec![
+            "fn cube(x: u32) -> u32 { square(x) * x }",
+        ]);
+    }
+
+    #[test]
+    fn keeps_scopes_apart() {
+        let mut codedb = codedb();
+        codedb.define("other", "square", "fn square() {}").unwrap();
+
+        assert!(codedb.referenced("nobody", "square(2)", &[]).is_empty());
+        assert_eq!(codedb.referenced("other", "square(2)", &[]), vec!["fn square() {}"]);
+        assert_eq!(codedb.names("#rust"), vec!["cube", "square", "unused"]);
+        assert_eq!(codedb.len("other"), 1);
+    }
+
+    #[test]
+    fn defines_and_undefines() {
+        let mut codedb = codedb();
+
+        assert!(codedb.define("#rust", "square", "fn square(x: u32) -> u32 { x.pow(2) }").unwrap());
+        assert!(!codedb.define("#rust", "half", "fn half(x: u32) -> u32 { x / 2 }").unwrap());
+        assert!(codedb.undefine("#rust", "unused").unwrap());
+        assert!(!codedb.undefine("#rust", "unused").unwrap());
+        assert_eq!(codedb.names("#rust"), vec!["cube", "half", "square"]);
+    }
+
+    #[test]
+    fn rolls_back_failed_saves() {
+        let mut codedb = CodeDb::open(Some("/nonexistent/playbot/codedb.json".into())).unwrap();
+
+        assert!(codedb.define("#rust", "square", "fn square() {}").is_err());
+        assert!(codedb.names("#rust").is_empty());
+    }
+
+    #[test]
+    fn saves_and_loads() {
+        let path = std::env::temp_dir().join(format!("playbot-codedb-{}.json", std::process::id()));
+        let mut codedb = CodeDb::open(Some(path.clone())).unwrap();
+        codedb.define("#rust", "square", "fn square() {}").unwrap();
+
+        let loaded = CodeDb::open(Some(path.clone()));
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.unwrap().names("#rust"), vec!["square"]);
+    }
+}
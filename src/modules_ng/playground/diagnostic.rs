@@ -0,0 +1,248 @@
+use regex::Regex;
+use super::SourceMap;
+
+lazy_static! {
+    static ref HEADER: Regex = Regex::new(r"^(?P<level>error|warning)(\[(?P<code>E\d+)\])?: (?P<message>.+)$").unwrap();
+    static ref LOCATION: Regex = Regex::new(r"^\s*--> (?P<file>.+):(?P<line>\d+):(?P<column>\d+)$").unwrap();
+    static ref SOURCE_LINE: Regex = Regex::new(r"^\s*(?P<line>\d+)\s*\| ?(?P<source>.*)$").unwrap();
+    static ref PRIMARY_LABEL: Regex = Regex::new(r"^\s*\|[\s|^\-]*\^+-*\s*(?P<label>\S.*)$").unwrap();
+}
+
+/// A compiler diagnostic parsed from rustc's human readable output, e.g.
+///
+/// ```text
+/// error[E0308]: mismatched types
+///  --> src/main.rs:6:21
+///   |
+/// 6 |         let x: u8 = "a";
+///   |                --   ^^^ expected `u8`, found `&str`
+/// ```
+pub struct Diagnostic {
+    pub level: String,
+    pub code: Option<String>,
+    pub message: String,
+    pub span: Option<Span>,
+    /// The source line the span points at.
+    pub source: Option<String>,
+    /// The note attached to the primary span, e.g. ``expected `u8`, found `&str` ``.
+    pub label: Option<String>,
+}
+
+pub struct Span {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Diagnostic {
+    pub fn parse_all(output: &str) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+        for line in output.lines() {
+            if super::is_diagnostic(line) {
+                if let Some(captures) = HEADER.captures(line) {
+                    diagnostics.push(Diagnostic {
+                        level: captures["level"].to_owned(),
+                        code: captures.name("code").map(|code| code.as_str().to_owned()),
+                        message: captures["message"].to_owned(),
+                        span: None,
+                        source: None,
+                        label: None,
+                    });
+                    continue;
+                }
+            }
+
+            let diagnostic = match diagnostics.last_mut() {
+                Some(diagnostic) => diagnostic,
+                None => continue,
+            };
+
+            if diagnostic.span.is_none() {
+                if let Some(captures) = LOCATION.captures(line) {
+                    diagnostic.span = Some(Span {
+                        file: captures["file"].to_owned(),
+                        line: captures["line"].parse().unwrap_or(0),
+                        column: captures["column"].parse().unwrap_or(0),
+                    });
+                }
+                continue;
+            }
+
+            if diagnostic.source.is_none() {
+                let span_line = diagnostic.span.as_ref().map_or(0, |span| span.line);
+
+                if let Some(captures) = SOURCE_LINE.captures(line) {
+                    if captures["line"].parse::<usize>().ok() == Some(span_line) {
+                        diagnostic.source = Some(captures["source"].to_owned());
+                    }
+                }
+                continue;
+            }
+
+            if diagnostic.label.is_none() {
+                if let Some(captures) = PRIMARY_LABEL.captures(line) {
+                    diagnostic.label = Some(captures["label"].to_owned());
+                }
+            }
+        }
+
+        diagnostics
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.level == "error"
+    }
+
+    /// One line describing the diagnostic, e.g.
+    /// ``error[E0308]: mismatched types at 1:13 ~~~ let x: u8 = "a"; (expected `u8`, found `&str`)``.
+    ///
+    /// The position refers to the user's input, it is left out if it lies in the template.
+    pub fn summary(&self, source_map: &SourceMap) -> String {
+        let mut summary = self.level.clone();
+
+        if let Some(code) = &self.code {
+            summary += &format!("[{}]", code);
+        }
+
+        summary += &format!(": {}", self.message);
+
+        let span = match &self.span {
+            Some(span) => span,
+            None => return summary,
+        };
+
+        let position = Some(span)
+            .filter(|span| span.file.ends_with("main.rs"))
+            .and_then(|span| source_map.to_input(span.line, span.column));
+
+        if let Some((line, column)) = position {
+            summary += &format!(" at {}:{}", line, column);
+        }
+
+        if let Some(source) = &self.source {
+            let source = around(source, span.column, 60);
+
+            if !source.is_empty() {
+                summary += &format!(" ~~~ {}", source);
+            }
+        }
+
+        if let Some(label) = &self.label {
+            summary += &format!(" ({})", label);
+        }
+
+        summary
+    }
+}
+
+/// At most `width` characters of the line, centered around the 1-based `column`.
+fn around(line: &str, column: usize, width: usize) -> String {
+    let chars = line.chars().count();
+
+    if chars <= width {
+        return line.trim().to_owned();
+    }
+
+    let start = column.saturating_sub(1).saturating_sub(width / 3).min(chars - width);
+    let mut excerpt = line.chars().skip(start).take(width).collect::<String>().trim().to_owned();
+
+    if start > 0 {
+        excerpt.insert(0, '…');
+    }
+
+    if start + width < chars {
+        excerpt.push('…');
+    }
+
+    excerpt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MISMATCHED_TYPES: &str = r#"   Compiling playground v0.0.1 (/playground)
+error[E0308]: mismatched types
+ --> src/main.rs:6:21
+  |
+6 |         let x: u8 = "a";
+  |                --   ^^^ expected `u8`, found `&str`
+  |                |
+  |                expected due to this
+
+error: aborting due to previous error
+"#;
+
+    #[test]
+    fn parses_error() {
+        let diagnostics = Diagnostic::parse_all(MISMATCHED_TYPES);
+        assert_eq!(diagnostics.len(), 1);
+
+        let diagnostic = &diagnostics[0];
+        let span = diagnostic.span.as_ref().unwrap();
+
+        assert!(diagnostic.is_error());
+        assert_eq!(diagnostic.code.as_ref().map(|code| code.as_str()), Some("E0308"));
+        assert_eq!(diagnostic.message, "mismatched types");
+        assert_eq!((span.file.as_str(), span.line, span.column), ("src/main.rs", 6, 21));
+        assert_eq!(diagnostic.source.as_ref().map(|source| source.as_str()), Some(r#"        let x: u8 = "a";"#));
+        assert_eq!(diagnostic.label.as_ref().map(|label| label.as_str()), Some("expected `u8`, found `&str`"));
+    }
+
+    #[test]
+    fn parses_several() {
+        let output = "warning: unused variable: `y`\n --> src/main.rs:2:9\n\nerror: expected expression, found `)`\n";
+        let diagnostics = Diagnostic::parse_all(output);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert!(!diagnostics[0].is_error());
+        assert_eq!(diagnostics[0].code, None);
+        assert!(diagnostics[1].is_error());
+        assert!(diagnostics[1].span.is_none());
+    }
+
+    #[test]
+    fn summary() {
+        let diagnostics = Diagnostic::parse_all(MISMATCHED_TYPES);
+
+        assert_eq!(
+            diagnostics[0].summary(&SourceMap::identity()),
+            r#"error[E0308]: mismatched types at 6:21 ~~~ let x: u8 = "a"; (expected `u8`, found `&str`)"#,
+        );
+    }
+
+    #[test]
+    fn summary_maps_position_to_input() {
+        let rendered = "fn main() {\n    println!(\"{:?}\", {\n        \u{0}code\u{0}\n    });\n}\n";
+        let input = "let x = 1;\nlet x: u8 = \"a\";";
+        let (_, source_map) = SourceMap::splice(rendered, input, &[("\u{0}code\u{0}", &[0..input.len()])]);
+        let output = "error[E0308]: mismatched types\n --> src/main.rs:4:13\n  |\n4 | let x: u8 = \"a\";\n  |             ^^^ expected `u8`, found `&str`\n";
+        let diagnostics = Diagnostic::parse_all(output);
+
+        assert_eq!(
+            diagnostics[0].summary(&source_map),
+            r#"error[E0308]: mismatched types at 2:13 ~~~ let x: u8 = "a"; (expected `u8`, found `&str`)"#,
+        );
+    }
+
+    #[test]
+    fn summary_leaves_out_template_positions() {
+        let input = "1";
+        let (_, source_map) = SourceMap::splice("fn main() {\n    \u{0}code\u{0}\n}\n", input, &[("\u{0}code\u{0}", &[0..1])]);
+        let diagnostics = Diagnostic::parse_all("error: oops\n --> src/main.rs:1:1\n");
+
+        assert_eq!(diagnostics[0].summary(&source_map), "error: oops");
+    }
+
+    #[test]
+    fn shortens_long_lines_around_column() {
+        let line = format!("{}x{}", "a".repeat(100), "b".repeat(100));
+        let excerpt = around(&line, 101, 60);
+
+        assert_eq!(excerpt.chars().count(), 62);
+        assert!(excerpt.starts_with('…') && excerpt.ends_with('…'));
+        assert_eq!(excerpt.find('x'), Some('…'.len_utf8() + 20));
+        assert_eq!(around("  short  ", 1, 60), "short");
+    }
+}
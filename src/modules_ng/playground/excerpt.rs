@@ -0,0 +1,136 @@
+/// The part of some output that fits into the channel.
+///
+/// Runs of identical lines are collapsed into one line,
+/// and lines that are too long are cut off.
+pub struct Excerpt {
+    pub lines: Vec<String>,
+    /// Lines that did not fit.
+    pub more_lines: usize,
+    /// Whether any of the shown lines had to be cut off.
+    pub shortened: bool,
+}
+
+impl Excerpt {
+    pub fn new<'a>(lines: impl IntoIterator<Item = &'a str>, max_lines: usize, max_line_chars: usize) -> Self {
+        let mut excerpt = Excerpt {
+            lines: Vec::new(),
+            more_lines: 0,
+            shortened: false,
+        };
+
+        for (line, count) in collapse(lines) {
+            if excerpt.lines.len() >= max_lines {
+                excerpt.more_lines += count;
+                continue;
+            }
+
+            let line = match count {
+                1 => line.to_owned(),
+                _ => format!("{} (repeated {} times)", line, count),
+            };
+
+            let (line, shortened) = shorten(line, max_line_chars);
+            excerpt.shortened |= shortened;
+            excerpt.lines.push(line);
+        }
+
+        excerpt
+    }
+
+    /// Whether the excerpt shows the whole output.
+    pub fn is_complete(&self) -> bool {
+        self.more_lines == 0 && !self.shortened
+    }
+
+    /// Marks the lines that were left out, e.g. `… 42 more lines`.
+    pub fn marker(&self) -> Option<String> {
+        match self.more_lines {
+            0 => None,
+            1 => Some("… 1 more line".into()),
+            n => Some(format!("… {} more lines", n)),
+        }
+    }
+
+    /// Prefixes `note` with the marker, if any lines were left out.
+    pub fn annotate(&self, note: &str) -> String {
+        match self.marker() {
+            Some(marker) => format!("{} {}", marker, note),
+            None => note.to_owned(),
+        }
+    }
+}
+
+/// Groups consecutive identical lines, yielding each line with the size of its run.
+fn collapse<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<(&'a str, usize)> {
+    let mut runs: Vec<(&str, usize)> = Vec::new();
+
+    for line in lines {
+        if let Some((last, count)) = runs.last_mut() {
+            if *last == line {
+                *count += 1;
+                continue;
+            }
+        }
+
+        runs.push((line, 1));
+    }
+
+    runs
+}
+
+fn shorten(line: String, max_chars: usize) -> (String, bool) {
+    match line.char_indices().nth(max_chars) {
+        Some((end, _)) => (format!("{}…", &line[..end]), true),
+        None => (line, false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_short_output() {
+        let excerpt = Excerpt::new(vec!["a", "b"], 5, 10);
+
+        assert_eq!(excerpt.lines, vec!["a", "b"]);
+        assert!(excerpt.is_complete());
+        assert_eq!(excerpt.marker(), None);
+        assert_eq!(excerpt.annotate("note"), "note");
+    }
+
+    #[test]
+    fn collapses_repeated_lines() {
+        let excerpt = Excerpt::new(vec!["a", "a", "a", "b", "a"], 5, 30);
+
+        assert_eq!(excerpt.lines, vec!["a (repeated 3 times)", "b", "a"]);
+        assert!(excerpt.is_complete());
+    }
+
+    #[test]
+    fn counts_every_left_out_line() {
+        let excerpt = Excerpt::new(vec!["a", "b", "b", "b", "c"], 1, 30);
+
+        assert_eq!(excerpt.lines, vec!["a"]);
+        assert_eq!(excerpt.more_lines, 4);
+        assert!(!excerpt.is_complete());
+        assert_eq!(excerpt.marker(), Some("… 4 more lines".to_owned()));
+        assert_eq!(excerpt.annotate("note"), "… 4 more lines note");
+    }
+
+    #[test]
+    fn one_more_line() {
+        let excerpt = Excerpt::new(vec!["a", "b"], 1, 30);
+
+        assert_eq!(excerpt.marker(), Some("… 1 more line".to_owned()));
+    }
+
+    #[test]
+    fn shortens_long_lines() {
+        let excerpt = Excerpt::new(vec!["äöüß", "ab"], 5, 2);
+
+        assert_eq!(excerpt.lines, vec!["äö…", "ab"]);
+        assert!(excerpt.shortened);
+        assert!(!excerpt.is_complete());
+    }
+}
@@ -0,0 +1,139 @@
+use ::playground::{Channel, Edition, CompileTarget};
+
+/// A flag in front of the code, e.g. `--nightly`.
+#[derive(Copy, Clone)]
+pub(crate) enum Flag {
+    Channel(Channel),
+    Version,
+    Bare,
+    AllocStats,
+    Display,
+    Pretty,
+    Type,
+    Bench,
+    Backtrace,
+    Debug,
+    Release,
+    Edition(Edition),
+    Compile(CompileTarget),
+    Intel,
+    Clippy,
+    Format,
+    Miri,
+    Test,
+    Expand,
+    Help,
+}
+
+/// Every flag by its name without the leading `--`.
+///
+/// Templates are selected the same way, so their names are checked against this table.
+const FLAGS: &[(&str, Flag)] = &[
+    ("stable", Flag::Channel(Channel::Stable)),
+    ("beta", Flag::Channel(Channel::Beta)),
+    ("nightly", Flag::Channel(Channel::Nightly)),
+    ("version", Flag::Version),
+    ("bare", Flag::Bare),
+    ("mini", Flag::Bare),
+    ("allocs", Flag::AllocStats),
+    ("alloc", Flag::AllocStats),
+    ("stats", Flag::AllocStats),
+    ("alloc-stats", Flag::AllocStats),
+    ("display", Flag::Display),
+    ("pretty", Flag::Pretty),
+    ("type", Flag::Type),
+    ("bench", Flag::Bench),
+    ("backtrace", Flag::Backtrace),
+    ("bt", Flag::Backtrace),
+    ("debug", Flag::Debug),
+    ("release", Flag::Release),
+    ("2015", Flag::Edition(Edition::E2015)),
+    ("2018", Flag::Edition(Edition::E2018)),
+    ("2021", Flag::Edition(Edition::E2021)),
+    ("2024", Flag::Edition(Edition::E2024)),
+    ("asm", Flag::Compile(CompileTarget::Asm)),
+    ("intel", Flag::Intel),
+    ("llvm-ir", Flag::Compile(CompileTarget::LlvmIr)),
+    ("ir", Flag::Compile(CompileTarget::LlvmIr)),
+    ("mir", Flag::Compile(CompileTarget::Mir)),
+    ("hir", Flag::Compile(CompileTarget::Hir)),
+    ("wasm", Flag::Compile(CompileTarget::Wasm)),
+    ("clippy", Flag::Clippy),
+    ("fmt", Flag::Format),
+    ("format", Flag::Format),
+    ("rustfmt", Flag::Format),
+    ("miri", Flag::Miri),
+    ("test", Flag::Test),
+    ("tests", Flag::Test),
+    ("expand", Flag::Expand),
+    ("help", Flag::Help),
+    ("h", Flag::Help),
+];
+
+impl Flag {
+    /// Parses a word in front of the code, e.g. `--nightly`.
+    pub fn parse(word: &str) -> Option<Flag> {
+        // Older spellings that don't follow the `--<name>` scheme
+        match word {
+            "VERSION" => return Some(Flag::Version),
+            "help" | "h" | "-h" | "-help" => return Some(Flag::Help),
+            _ => (),
+        }
+
+        word.get(2..)
+            .filter(|_| word.starts_with("--"))
+            .and_then(Flag::named)
+    }
+
+    /// The flag selected with `--<name>`.
+    pub fn named(name: &str) -> Option<Flag> {
+        FLAGS.iter()
+            .find(|(flag, _)| *flag == name)
+            .map(|&(_, flag)| flag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is(word: &str, expected: fn(Flag) -> bool) -> bool {
+        Flag::parse(word).map_or(false, expected)
+    }
+
+    #[test]
+    fn parses_flags() {
+        assert!(is("--nightly", |flag| match flag { Flag::Channel(Channel::Nightly) => true, _ => false }));
+        assert!(is("--2021", |flag| match flag { Flag::Edition(Edition::E2021) => true, _ => false }));
+        assert!(is("--ir", |flag| match flag { Flag::Compile(CompileTarget::LlvmIr) => true, _ => false }));
+        assert!(is("--bt", |flag| match flag { Flag::Backtrace => true, _ => false }));
+        assert!(is("--rustfmt", |flag| match flag { Flag::Format => true, _ => false }));
+    }
+
+    #[test]
+    fn parses_older_spellings() {
+        assert!(is("VERSION", |flag| match flag { Flag::Version => true, _ => false }));
+
+        for word in &["help", "h", "-h", "-help", "--help", "--h"] {
+            assert!(is(word, |flag| match flag { Flag::Help => true, _ => false }), "{}", word);
+        }
+    }
+
+    #[test]
+    fn rejects_other_words() {
+        assert!(Flag::parse("nightly").is_none());
+        assert!(Flag::parse("-nightly").is_none());
+        assert!(Flag::parse("--Nightly").is_none());
+        assert!(Flag::parse("--mytemplate").is_none());
+        assert!(Flag::parse("--").is_none());
+        assert!(Flag::parse("").is_none());
+        assert!(Flag::parse("1+1").is_none());
+    }
+
+    #[test]
+    fn looks_up_names_without_dashes() {
+        assert!(Flag::named("release").is_some());
+        assert!(Flag::named("--release").is_none());
+        assert!(Flag::named("VERSION").is_none());
+    }
+}
@@ -0,0 +1,180 @@
+use std::fmt;
+
+/// The outcome of a libtest run, parsed from its human readable output.
+pub struct TestSummary {
+    pub passed: usize,
+    pub failed: usize,
+    pub ignored: usize,
+    /// Names of the failed tests in the order they were reported.
+    pub failures: Vec<String>,
+}
+
+impl TestSummary {
+    /// Returns `None` if the output contains no `test result:` line,
+    /// e.g. because the tests failed to compile.
+    pub fn parse(output: &str) -> Option<Self> {
+        let mut summary = TestSummary {
+            passed: 0,
+            failed: 0,
+            ignored: 0,
+            failures: Vec::new(),
+        };
+        let mut has_result = false;
+
+        for line in output.lines() {
+            let line = line.trim();
+
+            if line.starts_with("test ") && line.ends_with(" ... FAILED") {
+                let name = &line["test ".len()..line.len() - " ... FAILED".len()];
+                summary.failures.push(name.to_owned());
+                continue;
+            }
+
+            if !line.starts_with("test result:") {
+                continue;
+            }
+
+            has_result = true;
+
+            // test result: FAILED. 3 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out
+            let counts = line.splitn(2, ". ").nth(1).unwrap_or("");
+
+            for count in counts.split(';') {
+                let mut parts = count.split_whitespace();
+                let n = parts.next().and_then(|n| n.parse::<usize>().ok());
+                let kind = parts.next();
+
+                match (n, kind) {
+                    (Some(n), Some("passed")) => summary.passed += n,
+                    (Some(n), Some("failed")) => summary.failed += n,
+                    (Some(n), Some("ignored")) => summary.ignored += n,
+                    _ => {},
+                }
+            }
+        }
+
+        if !has_result {
+            return None;
+        }
+
+        Some(summary)
+    }
+
+    pub fn total(&self) -> usize {
+        self.passed + self.failed + self.ignored
+    }
+}
+
+/// Whether `line` is one of libtest's own progress lines rather than something the tests printed.
+pub fn is_progress(line: &str) -> bool {
+    let line = line.trim();
+
+    line.is_empty()
+    || line.starts_with("running ")
+    || line.starts_with("test result:")
+    || (line.starts_with("test ") && line.contains(" ... "))
+}
+
+impl fmt::Display for TestSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let max_names = 3;
+
+        write!(f, "{} passed; {} failed", self.passed, self.failed)?;
+
+        if !self.failures.is_empty() {
+            let names = self.failures.iter()
+                .take(max_names)
+                .map(|name| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let more = if self.failures.len() > max_names { ", …" } else { "" };
+
+            write!(f, " ({}{})", names, more)?;
+        }
+
+        if self.ignored > 0 {
+            write!(f, "; {} ignored", self.ignored)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Real libtest output with one test of each outcome.
+    const OUTPUT: &str = "
+running 3 tests
+test adds ... ok
+test fails ... FAILED
+test slow ... ignored
+
+failures:
+
+---- fails stdout ----
+hello
+thread 'fails' panicked at src/main.rs:3:41:
+assertion `left == right` failed
+  left: 2
+ right: 3
+
+
+failures:
+    fails
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.01s
+";
+
+    #[test]
+    fn parses_counts_and_failures() {
+        let summary = TestSummary::parse(OUTPUT).unwrap();
+
+        assert_eq!((summary.passed, summary.failed, summary.ignored), (1, 1, 1));
+        assert_eq!(summary.failures, vec!["fails"]);
+        assert_eq!(summary.total(), 3);
+        assert_eq!(summary.to_string(), "1 passed; 1 failed (fails); 1 ignored");
+    }
+
+    #[test]
+    fn adds_up_several_results() {
+        let output = "test result: ok. 2 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out\n\
+                      test result: ok. 1 passed; 0 failed; 3 ignored; 0 measured; 0 filtered out\n";
+        let summary = TestSummary::parse(output).unwrap();
+
+        assert_eq!((summary.passed, summary.failed, summary.ignored), (3, 0, 3));
+        assert_eq!(summary.to_string(), "3 passed; 0 failed; 3 ignored");
+    }
+
+    #[test]
+    fn lists_at_most_three_failures() {
+        let output = "test a ... FAILED\ntest b ... FAILED\ntest c ... FAILED\ntest d ... FAILED\n\
+                      test result: FAILED. 0 passed; 4 failed; 0 ignored; 0 measured; 0 filtered out\n";
+
+        assert_eq!(TestSummary::parse(output).unwrap().to_string(), "0 passed; 4 failed (a, b, c, …)");
+    }
+
+    #[test]
+    fn needs_a_result() {
+        assert!(TestSummary::parse("error[E0425]: cannot find value `x` in this scope").is_none());
+        assert!(TestSummary::parse("running 1 test\ntest adds ... ok").is_none());
+    }
+
+    #[test]
+    fn tells_progress_from_test_output() {
+        let printed = OUTPUT.lines().filter(|line| !is_progress(line)).collect::<Vec<_>>();
+
+        assert_eq!(printed, vec![
+            "failures:",
+            "---- fails stdout ----",
+            "hello",
+            "thread 'fails' panicked at src/main.rs:3:41:",
+            "assertion `left == right` failed",
+            "  left: 2",
+            " right: 3",
+            "failures:",
+            "    fails",
+        ]);
+    }
+}
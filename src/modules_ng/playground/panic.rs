@@ -0,0 +1,207 @@
+use std::fmt;
+use itertools::Itertools;
+
+/// Name of the crate the playground compiles user code into.
+const USER_CRATE: &str = "playground::";
+
+/// A panic reported on stderr, optionally including a backtrace.
+pub struct Panic {
+    pub thread: String,
+    pub message: String,
+    pub location: Option<String>,
+    pub frames: Vec<Frame>,
+}
+
+pub struct Frame {
+    pub function: String,
+    pub location: Option<String>,
+}
+
+impl Panic {
+    /// Parses both the old (`panicked at 'msg', src/main.rs:1:2`)
+    /// and the new (`panicked at src/main.rs:1:2:\nmsg`) panic format.
+    pub fn parse(stderr: &str) -> Option<Self> {
+        let mut lines = stderr.lines().skip_while(|line| !is_panic_header(line)).peekable();
+        let header = lines.next()?;
+
+        let thread = header
+            .trim_start_matches("thread '")
+            .splitn(2, '\'')
+            .next()
+            .unwrap_or("")
+            .to_owned();
+        let rest = header.splitn(2, "panicked at ").nth(1).unwrap_or("");
+
+        let (message, location) = if rest.starts_with('\'') {
+            // thread 'main' panicked at 'explicit panic', src/main.rs:4:5
+            match rest.rfind("', ") {
+                Some(end) => (rest[1..end].to_owned(), Some(rest[end + 3..].to_owned())),
+                None => (rest.trim_matches('\'').to_owned(), None),
+            }
+        } else {
+            // thread 'main' panicked at src/main.rs:4:5:
+            // explicit panic
+            let location = rest.trim_end_matches(':').to_owned();
+            let mut message = Vec::new();
+
+            while let Some(&line) = lines.peek() {
+                if line.starts_with("note:") || line.starts_with("stack backtrace:") {
+                    break;
+                }
+
+                message.push(line.trim());
+                lines.next();
+            }
+
+            (message.join(" "), Some(location))
+        };
+
+        let mut frames: Vec<Frame> = Vec::new();
+
+        for line in lines.skip_while(|line| !line.starts_with("stack backtrace:")).skip(1) {
+            let line = line.trim();
+
+            if line.starts_with("at ") {
+                if let Some(frame) = frames.last_mut() {
+                    frame.location = Some(line["at ".len()..].trim_start_matches("./").to_owned());
+                }
+                continue;
+            }
+
+            // 12: playground::main
+            let function = match line.splitn(2, ": ").nth(1) {
+                Some(function) if line.chars().next().map_or(false, |c| c.is_digit(10)) => function,
+                _ => break,
+            };
+
+            frames.push(Frame {
+                function: function.to_owned(),
+                location: None,
+            });
+        }
+
+        Some(Panic {
+            thread,
+            message,
+            location,
+            frames,
+        })
+    }
+
+    /// Frames that belong to the user's code, innermost first.
+    pub fn user_frames(&self) -> impl Iterator<Item = &Frame> {
+        self.frames.iter().filter(|frame| frame.function.starts_with(USER_CRATE))
+    }
+
+    pub fn has_backtrace(&self) -> bool {
+        !self.frames.is_empty()
+    }
+}
+
+/// One line summary, e.g.
+/// `thread 'main' panicked at 'oops' (src/main.rs:2:5) | playground::foo ← playground::main`
+impl fmt::Display for Panic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let max_message_len = 200;
+        let max_frames = 3;
+
+        let message = match self.message.char_indices().nth(max_message_len) {
+            Some((end, _)) => format!("{}…", &self.message[..end]),
+            None => self.message.clone(),
+        };
+
+        write!(f, "thread '{}' panicked at '{}'", self.thread, message)?;
+
+        if let Some(location) = &self.location {
+            write!(f, " ({})", location)?;
+        }
+
+        let frames = self.user_frames()
+            .take(max_frames)
+            .map(|frame| match &frame.location {
+                Some(location) => format!("{} ({})", frame.function, location),
+                None => frame.function.clone(),
+            })
+            .join(" ← ");
+
+        if !frames.is_empty() {
+            write!(f, " | {}", frames)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn is_panic_header(line: &str) -> bool {
+    line.starts_with("thread '") && line.contains("' panicked at ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_old_format() {
+        let stderr = "   Compiling playground v0.0.1 (/playground)\n\
+                      thread 'main' panicked at 'explicit panic', src/main.rs:4:5\n\
+                      note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace\n";
+        let panic = Panic::parse(stderr).unwrap();
+
+        assert_eq!(panic.thread, "main");
+        assert_eq!(panic.message, "explicit panic");
+        assert_eq!(panic.location.as_ref().map(|location| location.as_str()), Some("src/main.rs:4:5"));
+        assert!(!panic.has_backtrace());
+        assert_eq!(panic.to_string(), "thread 'main' panicked at 'explicit panic' (src/main.rs:4:5)");
+    }
+
+    #[test]
+    fn parses_new_format() {
+        let stderr = "thread 'worker' panicked at src/main.rs:4:5:\n\
+                      index out of bounds:\n\
+                      the len is 0 but the index is 1\n\
+                      note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace\n";
+        let panic = Panic::parse(stderr).unwrap();
+
+        assert_eq!(panic.thread, "worker");
+        assert_eq!(panic.message, "index out of bounds: the len is 0 but the index is 1");
+        assert_eq!(panic.location.as_ref().map(|location| location.as_str()), Some("src/main.rs:4:5"));
+    }
+
+    #[test]
+    fn parses_backtrace() {
+        let stderr = "thread 'main' panicked at src/main.rs:2:5:\n\
+                      oops\n\
+                      stack backtrace:\n   \
+                         0: std::panicking::begin_panic\n             \
+                                   at /rustc/abc/library/std/src/panicking.rs:616:12\n   \
+                         1: playground::foo\n             \
+                                   at ./src/main.rs:2:5\n   \
+                         2: playground::main\n             \
+                                   at ./src/main.rs:6:5\n   \
+                         3: core::ops::function::FnOnce::call_once\n\
+                      note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.\n";
+        let panic = Panic::parse(stderr).unwrap();
+
+        assert!(panic.has_backtrace());
+        assert_eq!(panic.frames.len(), 4);
+        assert_eq!(panic.user_frames().map(|frame| frame.function.as_str()).collect::<Vec<_>>(), vec!["playground::foo", "playground::main"]);
+        assert_eq!(
+            panic.to_string(),
+            "thread 'main' panicked at 'oops' (src/main.rs:2:5) | playground::foo (src/main.rs:2:5) ← playground::main (src/main.rs:6:5)",
+        );
+    }
+
+    #[test]
+    fn shortens_long_messages() {
+        let stderr = format!("thread 'main' panicked at '{}', src/main.rs:1:1", "x".repeat(300));
+        let panic = Panic::parse(&stderr).unwrap();
+
+        assert_eq!(panic.to_string(), format!("thread 'main' panicked at '{}…' (src/main.rs:1:1)", "x".repeat(200)));
+    }
+
+    #[test]
+    fn no_panic() {
+        assert!(Panic::parse("error[E0308]: mismatched types\n").is_none());
+        assert!(Panic::parse("").is_none());
+    }
+}
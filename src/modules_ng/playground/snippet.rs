@@ -0,0 +1,500 @@
+use std::ops::Range;
+
+/// The user's input split into items, which are hoisted to the module level,
+/// and everything else (statements and the trailing expression), which stays in `main`.
+///
+/// E.g. `#[derive(Debug)] struct S; let s = S; s` puts the struct
+/// next to `main` and evaluates `let s = S; s` inside of it.
+pub struct Snippet {
+    pub items: Vec<Range<usize>>,
+    pub code: Vec<Range<usize>>,
+}
+
+impl Snippet {
+    /// Splits the given `range` of `input`, e.g. everything after the crate attributes.
+    pub fn split(input: &str, range: Range<usize>) -> Self {
+        let mut pieces = Vec::new();
+        let mut piece_start = range.start;
+
+        for end in boundaries(&input[range.clone()]) {
+            pieces.push(piece_start..range.start + end);
+            piece_start = range.start + end;
+        }
+
+        pieces.push(piece_start..range.end);
+
+        let mut snippet = Snippet {
+            items: Vec::new(),
+            code: Vec::new(),
+        };
+        let mut i = 0;
+
+        while i < pieces.len() {
+            // An item can span several pieces, e.g. `const X: u8 = { 1 };`.
+            // Parsing ever longer stretches is slow, so only pieces that look like an item get extended.
+            let item_end = if may_start_item(&input[pieces[i].clone()]) {
+                (i..pieces.len()).find(|&j| is_hoistable_item(&input[pieces[i].start..pieces[j].end]))
+            } else {
+                None
+            };
+
+            match item_end {
+                Some(j) => {
+                    snippet.items.push(trim(input, pieces[i].start..pieces[j].end));
+                    i = j + 1;
+                },
+                None => {
+                    let piece = pieces[i].clone();
+                    i += 1;
+
+                    // Keep consecutive statements together
+                    if let Some(last) = snippet.code.last_mut() {
+                        if last.end == piece.start {
+                            last.end = piece.end;
+                            continue;
+                        }
+                    }
+
+                    snippet.code.push(piece);
+                },
+            }
+        }
+
+        snippet.code = snippet.code.into_iter()
+            .map(|piece| trim(input, piece))
+            .filter(|piece| !piece.is_empty())
+            .collect();
+
+        // The template's block still needs something to evaluate
+        if snippet.code.is_empty() {
+            snippet.code.push(range.end..range.end);
+        }
+
+        snippet
+    }
+
+    /// Names of the hoisted items, e.g. `foo` for `fn foo() {}`.
+    pub fn item_names(&self, input: &str) -> Vec<String> {
+        self.items.iter()
+            .filter_map(|item| syn::parse_str::<syn::Item>(&input[item.clone()]).ok())
+            .filter_map(|item| match item {
+                syn::Item::Fn(item) => Some(item.ident),
+                syn::Item::Const(item) => Some(item.ident),
+                syn::Item::Static(item) => Some(item.ident),
+                syn::Item::Struct(item) => Some(item.ident),
+                syn::Item::Enum(item) => Some(item.ident),
+                syn::Item::Union(item) => Some(item.ident),
+                syn::Item::Type(item) => Some(item.ident),
+                syn::Item::Trait(item) => Some(item.ident),
+                syn::Item::Mod(item) => Some(item.ident),
+                syn::Item::Macro(item) => item.ident,
+                _ => None,
+            })
+            .map(|ident| ident.to_string())
+            .collect()
+    }
+}
+
+/// Splits the given `range` of `input` at each `;;` that is not nested in brackets,
+/// e.g. `a.sort() ;; a.sort_unstable()` into the two alternatives being compared.
+pub fn split_alternatives(input: &str, range: Range<usize>) -> Vec<Range<usize>> {
+    let code = &input[range.clone()];
+    let mut alternatives = Vec::new();
+    let mut start = 0;
+
+    for end in boundaries(code) {
+        let is_separator = end >= start + 2 && code[..end].ends_with(";;");
+
+        if is_separator {
+            alternatives.push(range.start + start..range.start + end - 2);
+            start = end;
+        }
+    }
+
+    alternatives.push(range.start + start..range.end);
+    alternatives
+}
+
+/// Words an item can start with, statements and expressions starting with any other word are no items.
+const ITEM_KEYWORDS: &[&str] = &[
+    "pub", "fn", "const", "static", "struct", "enum", "union", "type", "trait",
+    "impl", "mod", "use", "extern", "unsafe", "async", "default", "macro_rules",
+];
+
+/// Whether the code may begin with an item, judging by the first word after any comments.
+fn may_start_item(code: &str) -> bool {
+    let mut code = code.trim_start();
+
+    // Doc comments are attributes of the item, other comments are skipped
+    loop {
+        if code.starts_with("//") && !code.starts_with("///") {
+            code = code.find('\n').map_or("", |newline| &code[newline..]).trim_start();
+        } else if code.starts_with("/*") && !code.starts_with("/**") {
+            code = code[skip_block_comment(code.as_bytes(), 0)..].trim_start();
+        } else {
+            break;
+        }
+    }
+
+    if code.starts_with("#[") || code.starts_with("///") || code.starts_with("/**") {
+        return true;
+    }
+
+    let word = code.split(|c: char| !c.is_alphanumeric() && c != '_').next().unwrap_or("");
+
+    ITEM_KEYWORDS.contains(&word)
+}
+
+/// Whether the code is a single item that may be moved out of `main`.
+fn is_hoistable_item(code: &str) -> bool {
+    match syn::parse_str::<syn::Item>(code) {
+        // Macro invocations like `println!("…");` parse as items, too
+        Ok(syn::Item::Macro(item)) => item.mac.path.segments.len() == 1
+            && item.mac.path.segments[0].ident == "macro_rules",
+        Ok(syn::Item::Verbatim(_)) => false,
+        Ok(_) => true,
+        Err(_) => false,
+    }
+}
+
+fn trim(input: &str, range: Range<usize>) -> Range<usize> {
+    let code = &input[range.clone()];
+    let start = range.start + (code.len() - code.trim_start().len());
+    let end = range.end - (code.len() - code.trim_end().len());
+
+    start..end.max(start)
+}
+
+/// Byte offsets just past each `;` and `}` that is not nested in brackets,
+/// i.e. the places where an item or statement may end.
+fn boundaries(code: &str) -> Vec<usize> {
+    let bytes = code.as_bytes();
+    let mut boundaries = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = code[i..].find('\n').map_or(bytes.len(), |newline| i + newline);
+                continue;
+            },
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = skip_block_comment(bytes, i);
+                continue;
+            },
+            b'"' => {
+                i = skip_string(bytes, i + 1);
+                continue;
+            },
+            b'r' if is_raw_string_start(bytes, i) => {
+                i = skip_raw_string(bytes, i + 1);
+                continue;
+            },
+            b'\'' => {
+                i = skip_char_or_lifetime(code, i);
+                continue;
+            },
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' => depth = depth.saturating_sub(1),
+            b'}' => {
+                depth = depth.saturating_sub(1);
+
+                if depth == 0 {
+                    boundaries.push(i + 1);
+                }
+            },
+            b';' if depth == 0 => boundaries.push(i + 1),
+            _ => {},
+        }
+
+        i += 1;
+    }
+
+    boundaries
+}
+
+/// Skips a (possibly nested) block comment starting at `i`.
+fn skip_block_comment(bytes: &[u8], mut i: usize) -> usize {
+    let mut depth = 0;
+
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"/*") {
+            depth += 1;
+            i += 2;
+        } else if bytes[i..].starts_with(b"*/") {
+            depth -= 1;
+            i += 2;
+
+            if depth == 0 {
+                break;
+            }
+        } else {
+            i += 1;
+        }
+    }
+
+    i
+}
+
+/// Skips the rest of a string whose opening quote is just before `i`.
+fn skip_string(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+
+    bytes.len()
+}
+
+/// Whether the `r` at `i` starts a raw string like `r"…"`, `r#"…"#` or `br"…"`.
+fn is_raw_string_start(bytes: &[u8], i: usize) -> bool {
+    let is_ident = |byte: u8| byte.is_ascii_alphanumeric() || byte == b'_';
+    let prefix_ok = match i {
+        0 => true,
+        1 => !is_ident(bytes[0]) || bytes[0] == b'b',
+        _ => !is_ident(bytes[i - 1]) || (bytes[i - 1] == b'b' && !is_ident(bytes[i - 2])),
+    };
+    let hashes = bytes[i + 1..].iter().take_while(|&&byte| byte == b'#').count();
+
+    prefix_ok && bytes.get(i + 1 + hashes) == Some(&b'"')
+}
+
+/// Skips a raw string whose `r` is just before `i`.
+fn skip_raw_string(bytes: &[u8], i: usize) -> usize {
+    let hashes = bytes[i..].iter().take_while(|&&byte| byte == b'#').count();
+    let mut i = i + hashes + 1;
+
+    while i < bytes.len() {
+        if bytes[i] == b'"' && bytes[i + 1..].iter().take(hashes).filter(|&&byte| byte == b'#').count() == hashes {
+            return i + 1 + hashes;
+        }
+
+        i += 1;
+    }
+
+    bytes.len()
+}
+
+/// Skips a char literal like `'a'` or `'\n'`, or just the quote of a lifetime like `'a`.
+fn skip_char_or_lifetime(code: &str, i: usize) -> usize {
+    let rest = &code[i + 1..];
+    let first = match rest.chars().next() {
+        Some(first) => first,
+        None => return code.len(),
+    };
+
+    if first == '\\' {
+        // Escaped chars may be longer than one character, e.g. `'\u{1F600}'`
+        let escaped = first.len_utf8() + rest[1..].chars().next().map_or(0, char::len_utf8);
+
+        return match rest[escaped..].find('\'') {
+            Some(end) => i + 1 + escaped + end + 1,
+            None => code.len(),
+        };
+    }
+
+    if rest[first.len_utf8()..].starts_with('\'') {
+        return i + 1 + first.len_utf8() + 1;
+    }
+
+    i + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The items and code of the snippet as text.
+    fn split(input: &str) -> (Vec<&str>, Vec<&str>) {
+        let snippet = Snippet::split(input, 0..input.len());
+        let pieces = |ranges: &[Range<usize>]| ranges.iter().map(|range| &input[range.clone()]).collect();
+
+        (pieces(&snippet.items), pieces(&snippet.code))
+    }
+
+    fn alternatives(input: &str) -> Vec<&str> {
+        split_alternatives(input, 0..input.len()).into_iter()
+            .map(|range| &input[range])
+            .collect()
+    }
+
+    #[test]
+    fn hoists_functions() {
+        assert_eq!(split("fn foo() {} foo()"), (vec!["fn foo() {}"], vec!["foo()"]));
+    }
+
+    #[test]
+    fn hoists_unit_structs() {
+        assert_eq!(split("struct S; S"), (vec!["struct S;"], vec!["S"]));
+    }
+
+    #[test]
+    fn hoists_items_spanning_several_pieces() {
+        assert_eq!(split("const X: u8 = { 1 }; X"), (vec!["const X: u8 = { 1 };"], vec!["X"]));
+    }
+
+    #[test]
+    fn keeps_macro_invocations_in_main() {
+        assert_eq!(split("println!(\"a;\"); 1"), (vec![], vec!["println!(\"a;\"); 1"]));
+        assert_eq!(split("macro_rules! m { () => { 1 } } m!()"), (vec!["macro_rules! m { () => { 1 } }"], vec!["m!()"]));
+    }
+
+    #[test]
+    fn keeps_consecutive_statements_together() {
+        assert_eq!(
+            split("let x = 1; fn foo() {} let y = x; y"),
+            (vec!["fn foo() {}"], vec!["let x = 1;", "let y = x; y"]),
+        );
+    }
+
+    #[test]
+    fn empty_code() {
+        assert_eq!(split("fn foo() {}"), (vec!["fn foo() {}"], vec![""]));
+        assert_eq!(split(""), (vec![], vec![""]));
+    }
+
+    #[test]
+    fn split_respects_range() {
+        let input = "#![allow(unused)] struct S; S";
+        let snippet = Snippet::split(input, 17..input.len());
+
+        assert_eq!(&input[snippet.items[0].clone()], "struct S;");
+        assert_eq!(&input[snippet.code[0].clone()], "S");
+    }
+
+    #[test]
+    fn item_names() {
+        let input = "fn foo() {} struct S; macro_rules! m { () => {} } mod a {} let x = 1; x";
+        let snippet = Snippet::split(input, 0..input.len());
+
+        assert_eq!(snippet.item_names(input), vec!["foo", "S", "m", "a"]);
+    }
+
+    #[test]
+    fn hoists_items_after_comments_and_attributes() {
+        assert_eq!(
+            split("// helper\nfn foo() {} /// A unit\n#[derive(Debug)] struct S; foo()"),
+            (vec!["// helper\nfn foo() {}", "/// A unit\n#[derive(Debug)] struct S;"], vec!["foo()"]),
+        );
+        assert_eq!(split("/* a */ pub(crate) const X: u8 = 1; X"), (vec!["/* a */ pub(crate) const X: u8 = 1;"], vec!["X"]));
+    }
+
+    #[test]
+    fn items_start_with_keywords() {
+        assert!(may_start_item("  fn foo() {}"));
+        assert!(may_start_item("pub(crate) struct S;"));
+        assert!(may_start_item("#[derive(Debug)]"));
+        assert!(may_start_item("/// Docs\nfn foo() {}"));
+        assert!(may_start_item("// comment\n/* block */ impl S {}"));
+        assert!(may_start_item("macro_rules! m { () => {} }"));
+        assert!(!may_start_item("let x = 1;"));
+        assert!(!may_start_item("println!(\"fn\");"));
+        assert!(!may_start_item("function();"));
+        assert!(!may_start_item("// fn foo() {}\nx"));
+        assert!(!may_start_item(""));
+    }
+
+    #[test]
+    fn splits_long_input_quickly() {
+        let input = "let mut v = vec![1, 2, 3]; v.push(4);\n".repeat(200) + "v";
+        let snippet = Snippet::split(&input, 0..input.len());
+
+        assert!(snippet.items.is_empty());
+        assert_eq!(snippet.code.len(), 1);
+    }
+
+    #[test]
+    fn boundaries_after_semicolons_and_braces() {
+        assert_eq!(boundaries("a; b; c"), vec![2, 5]);
+        assert_eq!(boundaries("f(a; b); { x; } y"), vec![8, 15]);
+        assert!(boundaries("[1; 3]").is_empty());
+    }
+
+    #[test]
+    fn boundaries_skip_strings() {
+        assert_eq!(boundaries(r#"let s = "a;\"}"; s"#), vec![16]);
+        assert_eq!(boundaries(r##"let s = r#"a";"#; s"##), vec![17]);
+        assert_eq!(boundaries(r#"let s = br"a;"; s"#), vec![15]);
+    }
+
+    #[test]
+    fn boundaries_skip_comments() {
+        assert_eq!(boundaries("// a;\nb;"), vec![8]);
+        assert_eq!(boundaries("/* a; /* b; */ c; */ d;"), vec![23]);
+    }
+
+    #[test]
+    fn boundaries_skip_char_literals() {
+        assert_eq!(boundaries("let c = ';'; c"), vec![12]);
+        assert_eq!(boundaries("let c = '}'; c"), vec![12]);
+        assert_eq!(boundaries(r"let c = '\''; c"), vec![13]);
+    }
+
+    #[test]
+    fn boundaries_handle_lifetimes() {
+        let code = "fn f<'a>(x: &'a str) -> &'a str { x } f(\";\")";
+        assert_eq!(boundaries(code), vec![37]);
+    }
+
+    #[test]
+    fn raw_string_start() {
+        assert!(is_raw_string_start(b"r\"a\"", 0));
+        assert!(is_raw_string_start(b"r##\"a\"##", 0));
+        assert!(is_raw_string_start(b"br\"a\"", 1));
+        assert!(is_raw_string_start(b"(r\"a\"", 1));
+        assert!(!is_raw_string_start(b"bar\"a\"", 2));
+        assert!(!is_raw_string_start(b"r#a", 0));
+        assert!(!is_raw_string_start(b"r", 0));
+    }
+
+    #[test]
+    fn skips_raw_strings() {
+        let code = br##"r#"a"b"#; c"##;
+        assert_eq!(skip_raw_string(code, 1), 8);
+
+        let code = br#"r"a"; c"#;
+        assert_eq!(skip_raw_string(code, 1), 4);
+
+        // Unterminated
+        let code = br##"r#"a""##;
+        assert_eq!(skip_raw_string(code, 1), code.len());
+    }
+
+    #[test]
+    fn skips_chars_but_not_lifetimes() {
+        assert_eq!(skip_char_or_lifetime("'a' x", 0), 3);
+        assert_eq!(skip_char_or_lifetime("'ä' x", 0), 4);
+        assert_eq!(skip_char_or_lifetime(r"'\n' x", 0), 4);
+        assert_eq!(skip_char_or_lifetime(r"'\u{1F600}' x", 0), 11);
+        assert_eq!(skip_char_or_lifetime("'a str", 0), 1);
+        assert_eq!(skip_char_or_lifetime("'static str", 0), 1);
+        assert_eq!(skip_char_or_lifetime("'", 0), 1);
+    }
+
+    #[test]
+    fn splits_alternatives() {
+        assert_eq!(alternatives("a.sort() ;; a.sort_unstable()"), vec!["a.sort() ", " a.sort_unstable()"]);
+        assert_eq!(alternatives("x"), vec!["x"]);
+        assert_eq!(alternatives("a;; b;; c"), vec!["a", " b", " c"]);
+    }
+
+    #[test]
+    fn alternatives_ignore_nested_separators() {
+        assert_eq!(alternatives("f(|| { x;; }) ;; y"), vec!["f(|| { x;; }) ", " y"]);
+        assert_eq!(alternatives("[0u8;; 1]"), vec!["[0u8;; 1]"]);
+        assert_eq!(alternatives("\"a;;b\" ;; 1"), vec!["\"a;;b\" ", " 1"]);
+        assert_eq!(alternatives("// a;;\nb"), vec!["// a;;\nb"]);
+    }
+
+    #[test]
+    fn alternatives_respect_range() {
+        let input = "#![a] x ;; y";
+        let ranges = split_alternatives(input, 5..input.len());
+
+        assert_eq!(ranges, vec![5..8, 10..12]);
+    }
+}
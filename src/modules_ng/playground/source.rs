@@ -0,0 +1,463 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use ::playground::DEFAULT_BASE_URL;
+use failure::{Error, err_msg};
+use futures::prelude::*;
+use futures::future::{self, Either};
+use regex::Regex;
+use reqwest::r#async::{Client, Response};
+use serde::de::DeserializeOwned;
+use url::Url;
+
+/// Paste sites answer quickly or not at all, and the user is waiting for the reply.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Larger files are refused, the playground wouldn't run them anyway.
+const MAX_BYTES: u64 = 64 * 1024;
+/// API responses may describe several files, so they get more room.
+const MAX_API_BYTES: u64 = 1024 * 1024;
+
+lazy_static! {
+    static ref GIST_URL: Regex = Regex::new(
+        "^(https?://)?gist.github.com/([^/ ]+/)?(?P<id>[0-9a-f]+)/?$"
+    ).unwrap();
+
+    static ref RAW_GIST_URL: Regex = Regex::new(
+        "^(https?://)?gist.githubusercontent.com/[^/ ]+/[0-9a-f]+/raw(/.*)?"
+    ).unwrap();
+
+    static ref GITLAB_SNIPPET_URL: Regex = Regex::new(
+        r"^(https?://)?gitlab.com/(-/)?snippets/(?P<id>\d+)/?$"
+    ).unwrap();
+
+    static ref GITHUB_BLOB_URL: Regex = Regex::new(
+        r"^(https?://)?github.com/(?P<repo>[^/ ]+/[^/ ]+)/blob/(?P<path>[^ ]+)$"
+    ).unwrap();
+
+    static ref RAW_GITHUB_URL: Regex = Regex::new(
+        r"^(https?://)?raw.githubusercontent.com/[^ ]+$"
+    ).unwrap();
+}
+
+/// Fetches the code behind links like `https://gist.github.com/…`,
+/// so a whole program can be evaluated by passing its URL.
+///
+/// When a paste has several files, `<url>#file.rs` picks the one to run.
+pub struct Fetcher {
+    http: Client,
+    sources: Vec<Box<Source>>,
+}
+
+/// The code of a link, which is fetched without blocking the bot.
+pub type Fetch = Box<Future<Item = String, Error = Error>>;
+
+/// A site code can be fetched from.
+trait Source: Send + Sync {
+    /// Whether `url` (without the `#file.rs` part) belongs to this site.
+    fn matches(&self, url: &str) -> bool;
+
+    /// Fetches the code, picking `file` if there are several.
+    fn fetch(&self, http: &Client, url: &str, file: Option<&str>) -> Fetch;
+}
+
+impl Fetcher {
+    /// `playground_url` is the playground the bot uses, whose share links are recognized
+    /// in addition to the official playground's.
+    /// `raw_paste_hosts` are sites whose links can be fetched as they are, e.g. `paste.rs`.
+    pub fn new(playground_url: &str, raw_paste_hosts: &[String]) -> Result<Self, Error> {
+        Ok(Self {
+            http: Client::builder().timeout(TIMEOUT).build()?,
+            sources: vec![
+                Box::new(Gist),
+                Box::new(PlaygroundShare::new(playground_url)),
+                Box::new(GitLabSnippet),
+                Box::new(GitHubBlob),
+                Box::new(RawPaste { hosts: raw_paste_hosts.to_vec() }),
+            ],
+        })
+    }
+
+    /// Fetches the code if `body` is a link to it, `None` if `body` is code itself.
+    pub fn fetch(&self, body: &str) -> Option<Fetch> {
+        let body = body.trim();
+
+        if body.contains(char::is_whitespace) {
+            return None;
+        }
+
+        let (url, file) = match body.find('#') {
+            Some(hash) => (&body[..hash], Some(&body[hash + 1..]).filter(|file| file.ends_with(".rs"))),
+            None => (body, None),
+        };
+
+        let source = self.sources.iter().find(|source| source.matches(url))?;
+
+        Some(source.fetch(&self.http, url, file))
+    }
+}
+
+/// `gist.github.com/<user>/<id>` and the raw links of its files.
+struct Gist;
+
+impl Source for Gist {
+    fn matches(&self, url: &str) -> bool {
+        GIST_URL.is_match(url) || RAW_GIST_URL.is_match(url)
+    }
+
+    fn fetch(&self, http: &Client, url: &str, file: Option<&str>) -> Fetch {
+        if RAW_GIST_URL.is_match(url) {
+            return get(http, &with_scheme(url), MAX_BYTES);
+        }
+
+        match GIST_URL.captures(url) {
+            Some(captures) => fetch_gist(http, &captures["id"], file),
+            None => Box::new(future::err(err_msg("not a gist url"))),
+        }
+    }
+}
+
+/// Share links of the playground, which point to a gist.
+struct PlaygroundShare {
+    /// Share links of the configured and the official playground, e.g. `play.rust-lang.org/?gist=<id>`.
+    url: Regex,
+}
+
+impl PlaygroundShare {
+    fn new(playground_url: &str) -> Self {
+        let deployments = [playground_url, DEFAULT_BASE_URL].iter()
+            .map(|url| {
+                let url = url.trim_start_matches("https://").trim_start_matches("http://");
+                regex::escape(url.trim_end_matches('/'))
+            })
+            .collect::<Vec<_>>()
+            .join("|");
+        let url = Regex::new(&format!(r"^(https?://)?({})/?\?(.*&)?gist=(?P<id>[0-9a-f]+)(&.*)?$", deployments))
+            .expect("escaped urls form a valid pattern");
+
+        Self { url }
+    }
+}
+
+impl Source for PlaygroundShare {
+    fn matches(&self, url: &str) -> bool {
+        self.url.is_match(url)
+    }
+
+    fn fetch(&self, http: &Client, url: &str, file: Option<&str>) -> Fetch {
+        match self.url.captures(url) {
+            Some(captures) => fetch_gist(http, &captures["id"], file),
+            None => Box::new(future::err(err_msg("not a playground url"))),
+        }
+    }
+}
+
+fn fetch_gist(http: &Client, id: &str, file: Option<&str>) -> Fetch {
+    #[derive(Deserialize)]
+    struct GistInfo {
+        files: HashMap<String, GistFile>,
+    }
+
+    #[derive(Deserialize)]
+    struct GistFile {
+        content: String,
+        /// The API leaves out the content of large files.
+        truncated: bool,
+        raw_url: String,
+    }
+
+    let http = http.clone();
+    let file = file.map(str::to_owned);
+
+    Box::new(get_json::<GistInfo>(&http, &format!("https://api.github.com/gists/{}", id)).and_then(move |mut gist| {
+        let name = match pick_file(gist.files.keys().map(|name| name.as_str()), file.as_ref().map(|file| file.as_str())) {
+            Ok(name) => name.to_owned(),
+            Err(e) => return Either::A(future::err(e)),
+        };
+        let file = gist.files.remove(&name).expect("picked file exists");
+
+        if file.truncated {
+            return Either::B(get(&http, &file.raw_url, MAX_BYTES));
+        }
+
+        Either::A(future::result(check_size(file.content.len() as u64).map(|()| file.content)))
+    }))
+}
+
+/// `gitlab.com/snippets/<id>` or `gitlab.com/-/snippets/<id>`.
+struct GitLabSnippet;
+
+impl Source for GitLabSnippet {
+    fn matches(&self, url: &str) -> bool {
+        GITLAB_SNIPPET_URL.is_match(url)
+    }
+
+    fn fetch(&self, http: &Client, url: &str, file: Option<&str>) -> Fetch {
+        #[derive(Deserialize)]
+        struct Snippet {
+            raw_url: String,
+            /// Only present for snippets with several files.
+            #[serde(default)]
+            files: Vec<SnippetFile>,
+        }
+
+        #[derive(Deserialize)]
+        struct SnippetFile {
+            path: String,
+            raw_url: String,
+        }
+
+        let id = match GITLAB_SNIPPET_URL.captures(url) {
+            Some(captures) => captures["id"].to_owned(),
+            None => return Box::new(future::err(err_msg("not a snippet url"))),
+        };
+        let http = http.clone();
+        let file = file.map(str::to_owned);
+
+        Box::new(get_json::<Snippet>(&http, &format!("https://gitlab.com/api/v4/snippets/{}", id)).and_then(move |snippet| -> Fetch {
+            if snippet.files.is_empty() {
+                return get(&http, &snippet.raw_url, MAX_BYTES);
+            }
+
+            let name = match pick_file(snippet.files.iter().map(|file| file.path.as_str()), file.as_ref().map(|file| file.as_str())) {
+                Ok(name) => name,
+                Err(e) => return Box::new(future::err(e)),
+            };
+            let file = snippet.files.iter()
+                .find(|file| file.path == name)
+                .expect("picked file exists");
+
+            get(&http, &file.raw_url, MAX_BYTES)
+        }))
+    }
+}
+
+/// Files in GitHub repositories, either `github.com/<user>/<repo>/blob/…` or the raw link.
+struct GitHubBlob;
+
+impl Source for GitHubBlob {
+    fn matches(&self, url: &str) -> bool {
+        GITHUB_BLOB_URL.is_match(url) || RAW_GITHUB_URL.is_match(url)
+    }
+
+    fn fetch(&self, http: &Client, url: &str, _file: Option<&str>) -> Fetch {
+        if RAW_GITHUB_URL.is_match(url) {
+            return get(http, &with_scheme(url), MAX_BYTES);
+        }
+
+        match GITHUB_BLOB_URL.captures(url) {
+            Some(captures) => get(http, &format!("https://raw.githubusercontent.com/{}/{}", &captures["repo"], &captures["path"]), MAX_BYTES),
+            None => Box::new(future::err(err_msg("not a github url"))),
+        }
+    }
+}
+
+/// Paste sites whose links serve plain text, restricted to the configured hosts
+/// so the bot can't be used to request arbitrary URLs.
+struct RawPaste {
+    hosts: Vec<String>,
+}
+
+impl Source for RawPaste {
+    fn matches(&self, url: &str) -> bool {
+        let url = match Url::parse(url) {
+            Ok(url) => url,
+            Err(_) => return false,
+        };
+
+        let host = url.host_str().unwrap_or("");
+
+        (url.scheme() == "https" || url.scheme() == "http")
+        && self.hosts.iter().any(|allowed| allowed.eq_ignore_ascii_case(host))
+    }
+
+    fn fetch(&self, http: &Client, url: &str, _file: Option<&str>) -> Fetch {
+        Box::new(send(http, url).and_then(|resp| {
+            let is_text = resp.headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|content_type| content_type.to_str().ok())
+                .map_or(false, |content_type| content_type.starts_with("text/plain"));
+
+            // Most paste sites serve an HTML page unless the link is to the raw paste
+            if !is_text {
+                return Either::A(future::err(err_msg("the link doesn't point to plain text, try the paste's raw link")));
+            }
+
+            Either::B(read_limited(resp, MAX_BYTES))
+        }))
+    }
+}
+
+/// Picks the file to run: the requested one, the only `.rs` file or `main.rs`.
+fn pick_file<'a>(names: impl Iterator<Item = &'a str>, requested: Option<&str>) -> Result<&'a str, Error> {
+    let mut names = names.filter(|name| name.ends_with(".rs")).collect::<Vec<_>>();
+    names.sort();
+
+    if let Some(requested) = requested {
+        return names.into_iter()
+            .find(|name| *name == requested)
+            .ok_or_else(|| err_msg(format!("there is no file named {}", requested)));
+    }
+
+    match names.len() {
+        0 => Err(err_msg("no .rs file found")),
+        1 => Ok(names[0]),
+        _ => names.iter()
+            .find(|name| **name == "main.rs")
+            .cloned()
+            .ok_or_else(|| err_msg(format!("pick one of the files with <url>#file.rs: {}", names.join(", ")))),
+    }
+}
+
+fn with_scheme(url: &str) -> String {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        url.to_owned()
+    } else {
+        format!("https://{}", url)
+    }
+}
+
+fn send(http: &Client, url: &str) -> impl Future<Item = Response, Error = Error> {
+    http.get(url)
+    .send()
+    .and_then(|resp| resp.error_for_status())
+    .map_err(Error::from)
+}
+
+fn get(http: &Client, url: &str, limit: u64) -> Fetch {
+    Box::new(send(http, url).and_then(move |resp| read_limited(resp, limit)))
+}
+
+fn get_json<T: DeserializeOwned + 'static>(http: &Client, url: &str) -> impl Future<Item = T, Error = Error> {
+    get(http, url, MAX_API_BYTES).and_then(|json| Ok(serde_json::from_str(&json)?))
+}
+
+fn read_limited(resp: Response, limit: u64) -> impl Future<Item = String, Error = Error> {
+    if let Some(len) = resp.content_length() {
+        if len > limit {
+            return Either::A(future::err(too_large(limit)));
+        }
+    }
+
+    // The length may be missing or wrong, so stop reading as soon as the limit is exceeded
+    let bytes = resp.into_body()
+        .map_err(Error::from)
+        .fold(Vec::new(), move |mut bytes, chunk| {
+            bytes.extend_from_slice(&chunk);
+
+            if bytes.len() as u64 > limit {
+                return Err(too_large(limit));
+            }
+
+            Ok(bytes)
+        });
+
+    // Only decoded once complete, a chunk may end in the middle of a character
+    Either::B(bytes.and_then(|bytes| {
+        String::from_utf8(bytes).map_err(|_| err_msg("the code isn't valid UTF-8"))
+    }))
+}
+
+fn check_size(len: u64) -> Result<(), Error> {
+    if len > MAX_BYTES {
+        return Err(too_large(MAX_BYTES));
+    }
+
+    Ok(())
+}
+
+fn too_large(limit: u64) -> Error {
+    err_msg(format!("the code is larger than {} KiB", limit / 1024))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pick<'a>(names: &[&'a str], requested: Option<&str>) -> Result<&'a str, String> {
+        pick_file(names.iter().cloned(), requested).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn picks_the_only_rust_file() {
+        assert_eq!(pick(&["README.md", "lib.rs"], None), Ok("lib.rs"));
+    }
+
+    #[test]
+    fn picks_main_among_several() {
+        assert_eq!(pick(&["util.rs", "main.rs"], None), Ok("main.rs"));
+        assert_eq!(pick(&["b.rs", "a.rs"], None), Err("pick one of the files with <url>#file.rs: a.rs, b.rs".to_owned()));
+    }
+
+    #[test]
+    fn picks_the_requested_file() {
+        assert_eq!(pick(&["util.rs", "main.rs"], Some("util.rs")), Ok("util.rs"));
+        assert!(pick(&["main.rs"], Some("util.rs")).is_err());
+        assert!(pick(&["notes.txt"], Some("notes.txt")).is_err());
+    }
+
+    #[test]
+    fn needs_a_rust_file() {
+        assert!(pick(&[], None).is_err());
+        assert!(pick(&["notes.txt"], None).is_err());
+    }
+
+    #[test]
+    fn matches_gists() {
+        assert!(Gist.matches("https://gist.github.com/rust-play/0a1b2c3d"));
+        assert!(Gist.matches("gist.github.com/0a1b2c3d/"));
+        assert!(Gist.matches("https://gist.githubusercontent.com/user/0a1b2c3d/raw/main.rs"));
+        assert!(!Gist.matches("https://gist.github.com/user/not-hex"));
+        assert!(!Gist.matches("https://example.com/gist.github.com/0a1b2c3d"));
+    }
+
+    #[test]
+    fn matches_playground_share_links() {
+        let share = PlaygroundShare::new(DEFAULT_BASE_URL);
+
+        assert!(share.matches("https://play.rust-lang.org/?version=stable&mode=debug&gist=0a1b2c3d"));
+        assert!(share.matches("play.rust-lang.org/?gist=0a1b2c3d&edition=2018"));
+        assert!(!share.matches("https://play.rust-lang.org/?version=stable"));
+        assert!(!share.matches("https://playXrust-lang.org/?gist=0a1b2c3d"));
+    }
+
+    #[test]
+    fn matches_share_links_of_the_configured_playground() {
+        let share = PlaygroundShare::new("http://localhost:5000/play/");
+
+        assert!(share.matches("http://localhost:5000/play/?gist=0a1b2c3d&version=stable&mode=debug"));
+        assert!(share.matches("https://play.rust-lang.org/?gist=0a1b2c3d"));
+        assert!(!share.matches("http://localhost:5000/?gist=0a1b2c3d"));
+        assert!(!share.matches("http://example.com/?gist=0a1b2c3d"));
+    }
+
+    #[test]
+    fn matches_gitlab_snippets() {
+        assert!(GitLabSnippet.matches("https://gitlab.com/snippets/1234"));
+        assert!(GitLabSnippet.matches("https://gitlab.com/-/snippets/1234/"));
+        assert!(!GitLabSnippet.matches("https://gitlab.com/user/repo"));
+    }
+
+    #[test]
+    fn matches_github_files() {
+        assert!(GitHubBlob.matches("https://github.com/rust-lang/rust/blob/master/src/main.rs"));
+        assert!(GitHubBlob.matches("https://raw.githubusercontent.com/rust-lang/rust/master/src/main.rs"));
+        assert!(!GitHubBlob.matches("https://github.com/rust-lang/rust"));
+    }
+
+    #[test]
+    fn matches_configured_paste_hosts_only() {
+        let raw_paste = RawPaste { hosts: vec!["paste.rs".to_owned()] };
+
+        assert!(raw_paste.matches("https://paste.rs/abc"));
+        assert!(raw_paste.matches("http://PASTE.rs/abc"));
+        assert!(!raw_paste.matches("https://paste.rs.example.com/abc"));
+        assert!(!raw_paste.matches("ftp://paste.rs/abc"));
+        assert!(!raw_paste.matches("paste.rs/abc"));
+    }
+
+    #[test]
+    fn adds_missing_scheme() {
+        assert_eq!(with_scheme("gist.github.com/0a1b"), "https://gist.github.com/0a1b");
+        assert_eq!(with_scheme("http://paste.rs/abc"), "http://paste.rs/abc");
+    }
+}
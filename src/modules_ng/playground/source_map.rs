@@ -0,0 +1,204 @@
+use std::ops::Range;
+use regex::{Regex, Captures};
+
+lazy_static! {
+    /// A position in the program, e.g. `src/main.rs:6:21` or `/playground/src/main.rs:6:9`.
+    static ref POSITION: Regex = Regex::new(r"\b(?P<file>(?:[\w.-]+/)*main\.rs):(?P<line>\d+):(?P<column>\d+)").unwrap();
+}
+
+/// Where the user's code ended up in the program sent to the playground,
+/// so positions reported by rustc can be translated back to the user's input.
+#[derive(Clone, Debug)]
+pub struct SourceMap {
+    segments: Vec<Segment>,
+}
+
+/// A piece of the user's input that was inserted into the program as a whole.
+#[derive(Clone, Debug)]
+struct Segment {
+    /// Line and column in the program where the piece starts, both 1-based.
+    program_start: (usize, usize),
+    /// Line and column in the user's input where the piece starts.
+    input_start: (usize, usize),
+    /// Number of lines the piece spans.
+    lines: usize,
+}
+
+impl SourceMap {
+    /// The program is the user's input as-is.
+    pub fn identity() -> Self {
+        Self {
+            segments: vec![Segment {
+                program_start: (1, 1),
+                input_start: (1, 1),
+                lines: usize::max_value(),
+            }],
+        }
+    }
+
+    /// Replaces each marker in the rendered template with the given pieces of `input`,
+    /// one piece per line, and records where they were inserted.
+    pub fn splice(rendered: &str, input: &str, insertions: &[(&str, &[Range<usize>])]) -> (String, Self) {
+        let mut insertions = insertions.iter()
+            .map(|&(marker, pieces)| {
+                let offset = rendered.find(marker).expect("template contains the marker");
+                (offset, marker, pieces)
+            })
+            .collect::<Vec<_>>();
+        insertions.sort_by_key(|&(offset, _, _)| offset);
+
+        let mut program = String::new();
+        let mut segments = Vec::new();
+        let mut rest = 0;
+
+        for (offset, marker, pieces) in insertions {
+            program.push_str(&rendered[rest..offset]);
+
+            for (i, piece) in pieces.iter().enumerate() {
+                if i > 0 {
+                    program.push('\n');
+                }
+
+                let code = &input[piece.clone()];
+
+                segments.push(Segment {
+                    program_start: position(&program, program.len()),
+                    input_start: position(input, piece.start),
+                    lines: code.lines().count().max(1),
+                });
+
+                program.push_str(code);
+            }
+
+            rest = offset + marker.len();
+        }
+
+        program.push_str(&rendered[rest..]);
+
+        (program, Self { segments })
+    }
+
+    /// Translates a position in the program to one in the user's input,
+    /// or `None` if it lies in the template.
+    pub fn to_input(&self, line: usize, column: usize) -> Option<(usize, usize)> {
+        self.segments.iter().find_map(|segment| segment.to_input(line, column))
+    }
+
+    /// Rewrites positions in compiler or panic output to refer to the user's input.
+    /// Positions in the template are left alone.
+    pub fn rewrite(&self, output: &str) -> String {
+        POSITION.replace_all(output, |captures: &Captures| {
+            let line = captures["line"].parse().unwrap_or(0);
+            let column = captures["column"].parse().unwrap_or(0);
+
+            match self.to_input(line, column) {
+                Some((line, column)) => format!("{}:{}:{}", &captures["file"], line, column),
+                None => captures[0].to_owned(),
+            }
+        }).into_owned()
+    }
+}
+
+impl Segment {
+    fn to_input(&self, line: usize, column: usize) -> Option<(usize, usize)> {
+        let (start_line, start_column) = self.program_start;
+        let (input_line, input_column) = self.input_start;
+
+        if line < start_line || line - start_line >= self.lines {
+            return None;
+        }
+
+        // Only the first line is shifted by the template's indentation
+        if line == start_line {
+            if column < start_column {
+                return None;
+            }
+
+            return Some((input_line, column - start_column + input_column));
+        }
+
+        Some((input_line + line - start_line, column))
+    }
+}
+
+/// Line and column of the byte `offset` in `text`, both 1-based.
+fn position(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    let column = before[line_start..].chars().count() + 1;
+
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE: &str = "fn main() {\n    \u{0}code\u{0}\n}\n";
+
+    #[test]
+    fn identity() {
+        let source_map = SourceMap::identity();
+
+        assert_eq!(source_map.to_input(1, 1), Some((1, 1)));
+        assert_eq!(source_map.to_input(5, 3), Some((5, 3)));
+    }
+
+    #[test]
+    fn splices_code_into_template() {
+        let input = "let x = 1;\nx";
+        let (program, _) = SourceMap::splice(TEMPLATE, input, &[("\u{0}code\u{0}", &[0..input.len()])]);
+
+        assert_eq!(program, "fn main() {\n    let x = 1;\nx\n}\n");
+    }
+
+    #[test]
+    fn maps_positions_back_to_input() {
+        let input = "let x = 1;\nx";
+        let (_, source_map) = SourceMap::splice(TEMPLATE, input, &[("\u{0}code\u{0}", &[0..input.len()])]);
+
+        // Only the first line is indented by the template
+        assert_eq!(source_map.to_input(2, 9), Some((1, 5)));
+        assert_eq!(source_map.to_input(3, 1), Some((2, 1)));
+
+        // Positions in the template
+        assert_eq!(source_map.to_input(1, 1), None);
+        assert_eq!(source_map.to_input(2, 2), None);
+        assert_eq!(source_map.to_input(4, 1), None);
+    }
+
+    #[test]
+    fn splices_several_markers_in_template_order() {
+        let rendered = "\u{0}items\u{0}\nfn main() {\n    \u{0}code\u{0}\n}\n";
+        let input = "struct S; let s = S; fn f() {}";
+        let items: &[Range<usize>] = &[0..9, 21..30];
+        let code: &[Range<usize>] = &[10..20];
+
+        let (program, source_map) = SourceMap::splice(rendered, input, &[("\u{0}code\u{0}", code), ("\u{0}items\u{0}", items)]);
+
+        assert_eq!(program, "struct S;\nfn f() {}\nfn main() {\n    let s = S;\n}\n");
+        assert_eq!(source_map.to_input(1, 8), Some((1, 8)));
+        assert_eq!(source_map.to_input(2, 4), Some((1, 25)));
+        assert_eq!(source_map.to_input(4, 9), Some((1, 15)));
+        assert_eq!(source_map.to_input(3, 1), None);
+    }
+
+    #[test]
+    fn rewrites_positions_in_output() {
+        let input = "let x = 1;\nx";
+        let (_, source_map) = SourceMap::splice(TEMPLATE, input, &[("\u{0}code\u{0}", &[0..input.len()])]);
+        let output = "--> src/main.rs:2:9\nat /playground/src/main.rs:3:1\nin src/main.rs:1:1\nin src/lib.rs:2:9";
+
+        assert_eq!(
+            source_map.rewrite(output),
+            "--> src/main.rs:1:5\nat /playground/src/main.rs:2:1\nin src/main.rs:1:1\nin src/lib.rs:2:9",
+        );
+    }
+
+    #[test]
+    fn columns_count_chars() {
+        assert_eq!(position("äb\ncd", 2), (1, 2));
+        assert_eq!(position("äb\ncd", 5), (2, 2));
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use failure::{Error, err_msg};
+use regex::Regex;
+use crate::TemplateConfig;
+use crate::modules_ng::Flag;
+
+lazy_static! {
+    // `{{…}}` would clash with escaped braces in format strings
+    static ref PLACEHOLDER: Regex = Regex::new(r"\$\{\s*(?P<name>\w+)\s*\}").unwrap();
+    static ref NAME: Regex = Regex::new(r"^[a-z0-9][a-z0-9_-]*$").unwrap();
+}
+
+/// Templates defined by the operator, each selected with `--<name>`.
+#[derive(Default)]
+pub struct Templates {
+    templates: HashMap<String, Arc<CustomTemplate>>,
+}
+
+impl Templates {
+    /// Loads every `<name>.rs` file in `dir` as well as the templates defined in the config.
+    pub fn load(dir: Option<&Path>, configs: &[TemplateConfig]) -> Result<Self, Error> {
+        let mut templates = Self::default();
+
+        if let Some(dir) = dir {
+            let mut paths = fs::read_dir(dir)
+                .map_err(|e| err_msg(format!("failed to read template directory {}: {}", dir.display(), e)))?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()?;
+            paths.sort();
+
+            for path in paths {
+                if path.extension().map_or(true, |extension| extension != "rs") {
+                    continue;
+                }
+
+                let name = path.file_stem().and_then(|name| name.to_str()).unwrap_or("").to_owned();
+                let source = read_template(&path)?;
+                // A leading `//! …` line describes the template
+                let description = source.lines().next()
+                    .filter(|line| line.starts_with("//!"))
+                    .map(|line| line["//!".len()..].trim().to_owned());
+
+                templates.insert(CustomTemplate::new(name, description, source)?)?;
+            }
+        }
+
+        for config in configs {
+            let source = match (&config.source, &config.file) {
+                (Some(source), None) => source.clone(),
+                (None, Some(file)) => read_template(file)?,
+                _ => return Err(err_msg(format!("template '{}' needs either `source` or `file`", config.name))),
+            };
+
+            templates.insert(CustomTemplate::new(config.name.clone(), config.description.clone(), source)?)?;
+        }
+
+        Ok(templates)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<CustomTemplate>> {
+        self.templates.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<CustomTemplate>> {
+        self.templates.values()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.templates.is_empty()
+    }
+
+    fn insert(&mut self, template: CustomTemplate) -> Result<(), Error> {
+        if self.templates.contains_key(&template.name) {
+            return Err(err_msg(format!("template '{}' is defined twice", template.name)));
+        }
+
+        self.templates.insert(template.name.clone(), Arc::new(template));
+
+        Ok(())
+    }
+}
+
+/// A template loaded at runtime.
+///
+/// The user's code is inserted at `${code}`. Optionally, `${items}` receives
+/// the items (functions, structs, …) from the code, which are then moved out of `${code}`,
+/// and `${crate_attrs}` the inner attributes from the start of the code.
+#[derive(Debug, PartialEq)]
+pub struct CustomTemplate {
+    name: String,
+    description: Option<String>,
+    source: String,
+    placeholders: Placeholders,
+}
+
+/// The optional placeholders a template uses, `${code}` is always present.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Placeholders {
+    pub items: bool,
+    pub crate_attrs: bool,
+}
+
+impl CustomTemplate {
+    pub fn new(name: String, description: Option<String>, source: String) -> Result<Self, Error> {
+        if !NAME.is_match(&name) {
+            return Err(err_msg(format!("template name '{}' may only contain a-z, 0-9, '-' and '_'", name)));
+        }
+
+        // A template named like a flag could never be selected
+        if Flag::named(&name).is_some() {
+            return Err(err_msg(format!("template name '{}' is already used by a flag", name)));
+        }
+
+        let mut counts = HashMap::new();
+
+        for captures in PLACEHOLDER.captures_iter(&source) {
+            let placeholder = captures["name"].to_owned();
+
+            match placeholder.as_str() {
+                "code" | "items" | "crate_attrs" => *counts.entry(placeholder).or_insert(0) += 1,
+                _ => return Err(err_msg(format!("template '{}' has an unknown placeholder ${{{}}}", name, placeholder))),
+            }
+        }
+
+        if counts.get("code") != Some(&1) {
+            return Err(err_msg(format!("template '{}' must contain ${{code}} exactly once", name)));
+        }
+
+        if counts.values().any(|&count| count > 1) {
+            return Err(err_msg(format!("template '{}' may contain each placeholder only once", name)));
+        }
+
+        let placeholders = Placeholders {
+            items: counts.contains_key("items"),
+            crate_attrs: counts.contains_key("crate_attrs"),
+        };
+
+        Ok(Self {
+            name,
+            description,
+            source,
+            placeholders,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_ref().map(|description| description.as_str())
+    }
+
+    pub fn placeholders(&self) -> Placeholders {
+        self.placeholders
+    }
+
+    /// Fills in the placeholders, unused values are ignored.
+    pub fn render(&self, crate_attrs: &str, items: &str, code: &str) -> String {
+        PLACEHOLDER.replace_all(&self.source, |captures: &regex::Captures| {
+            match &captures["name"] {
+                "crate_attrs" => crate_attrs,
+                "items" => items,
+                _ => code,
+            }.to_owned()
+        }).into_owned()
+    }
+}
+
+fn read_template(path: &Path) -> Result<String, Error> {
+    fs::read_to_string(path)
+        .map_err(|e| err_msg(format!("failed to read template {}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(name: &str, source: &str) -> Result<CustomTemplate, Error> {
+        CustomTemplate::new(name.to_owned(), None, source.to_owned())
+    }
+
+    fn config(name: &str, source: &str) -> TemplateConfig {
+        TemplateConfig {
+            name: name.to_owned(),
+            description: None,
+            source: Some(source.to_owned()),
+            file: None,
+        }
+    }
+
+    #[test]
+    fn accepts_valid_templates() {
+        let template = template("dbg-2", "fn main() { println!(\"{{}}\"); ${code} }").unwrap();
+
+        assert_eq!(template.name(), "dbg-2");
+        assert_eq!(template.placeholders(), Placeholders::default());
+    }
+
+    #[test]
+    fn finds_optional_placeholders() {
+        let template = template("full", "${crate_attrs}\n${ items }\nfn main() { ${code} }").unwrap();
+
+        assert_eq!(template.placeholders(), Placeholders { items: true, crate_attrs: true });
+    }
+
+    #[test]
+    fn rejects_bad_names() {
+        assert!(template("Upper", "${code}").is_err());
+        assert!(template("-dash", "${code}").is_err());
+        assert!(template("with space", "${code}").is_err());
+        assert!(template("", "${code}").is_err());
+    }
+
+    #[test]
+    fn rejects_flag_names() {
+        assert!(template("nightly", "${code}").is_err());
+        assert!(template("release", "${code}").is_err());
+        assert!(template("h", "${code}").is_err());
+        assert!(template("2018", "${code}").is_err());
+    }
+
+    #[test]
+    fn rejects_bad_placeholders() {
+        assert!(template("t", "fn main() {}").is_err());
+        assert!(template("t", "${code} ${code}").is_err());
+        assert!(template("t", "${items} ${items} ${code}").is_err());
+        assert!(template("t", "${code} ${unknown}").is_err());
+    }
+
+    #[test]
+    fn renders_placeholders() {
+        let template = template("t", "${crate_attrs}|${ items }|{{x}}|${code}").unwrap();
+
+        assert_eq!(template.render("A", "I", "C"), "A|I|{{x}}|C");
+    }
+
+    #[test]
+    fn loads_configured_templates() {
+        let templates = Templates::load(None, &[config("one", "${code}"), config("two", "{ ${code} }")]).unwrap();
+
+        assert!(!templates.is_empty());
+        assert_eq!(templates.get("two").map(|template| template.render("", "", "1")), Some("{ 1 }".to_owned()));
+        assert!(templates.get("three").is_none());
+    }
+
+    #[test]
+    fn rejects_duplicate_templates() {
+        assert!(Templates::load(None, &[config("one", "${code}"), config("one", "${code}")]).is_err());
+    }
+
+    #[test]
+    fn needs_source_or_file() {
+        let mut config = config("one", "${code}");
+        config.file = Some("one.rs".into());
+        assert!(Templates::load(None, &[config.clone()]).is_err());
+
+        config.source = None;
+        config.file = None;
+        assert!(Templates::load(None, &[config]).is_err());
+    }
+
+    #[test]
+    fn loads_template_directory() {
+        let dir = std::env::temp_dir().join(format!("playbot-templates-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("wrapped.rs"), "//! Wraps the code\nfn main() { ${code} }").unwrap();
+        fs::write(dir.join("notes.txt"), "not a template").unwrap();
+
+        let templates = Templates::load(Some(&dir), &[]);
+        fs::remove_dir_all(&dir).unwrap();
+        let templates = templates.unwrap();
+
+        assert_eq!(templates.iter().count(), 1);
+        assert_eq!(templates.get("wrapped").and_then(|template| template.description()), Some("Wraps the code"));
+    }
+}
@@ -1,5 +1,6 @@
 #![allow(unreachable_code)]
 {crate_attrs}
+{items}
 
 fn main() {{
     println!("{{:?}}", {{
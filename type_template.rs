@@ -0,0 +1,13 @@
+#![allow(unreachable_code)]
+{crate_attrs}
+{items}
+
+fn main() {{
+    fn __type_name_of<T>(_: &T) -> &'static str {{
+        std::any::type_name::<T>()
+    }}
+
+    println!("{{}}", __type_name_of(&{{
+        {code}
+    }}));
+}}